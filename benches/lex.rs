@@ -0,0 +1,52 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use md_to_tui::parser::lexer::Lexer;
+
+/// a markdown-ish document of `paragraphs` repeated blocks
+fn doc(paragraphs: usize) -> String {
+    let mut out = String::new();
+    for i in 0..paragraphs {
+        out.push_str(&format!("## section {i}\n\n"));
+        out.push_str("some *emphasised* text with `code` and a [link](http://example.com)\n\n");
+        out.push_str("- one\n- two\n- three\n\n");
+    }
+    out
+}
+
+fn lex_docs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lex");
+    for (name, paragraphs) in [("small", 10), ("medium", 200), ("large", 2000)] {
+        let input = doc(paragraphs);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| Lexer::new().parse(black_box(&input)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn ascii_fast_path(c: &mut Criterion) {
+    // a ~1MB ASCII document takes the table-driven fast path, a single
+    // multibyte char at the end forces the general path over the whole
+    // input
+    let mut ascii = String::new();
+    while ascii.len() < 1 << 20 {
+        ascii.push_str("plain words, more plain words and yet more words\n");
+    }
+    let mut general = ascii.clone();
+    general.push('é');
+
+    let mut group = c.benchmark_group("ascii_fast_path");
+    group.throughput(Throughput::Bytes(ascii.len() as u64));
+    group.bench_function("ascii", |b| {
+        b.iter(|| Lexer::new().parse(black_box(&ascii)).unwrap())
+    });
+    group.bench_function("general", |b| {
+        b.iter(|| Lexer::new().parse(black_box(&general)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, lex_docs, ascii_fast_path);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use md_to_tui::parser::lexer::Lexer;
+
+fuzz_target!(|data: &[u8]| {
+    // arbitrary bytes must produce tokens or an error, never a panic
+    let _ = Lexer::from_bytes(data).tokenize();
+    if let Ok(input) = core::str::from_utf8(data) {
+        let _ = Lexer::new().parse(input);
+    }
+});
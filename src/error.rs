@@ -1,21 +1,77 @@
-use std::fmt::Display;
+use alloc::{format, string::String};
+use core::fmt::Display;
 
-#[derive(Debug, thiserror::Error, PartialEq, Clone, Default)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum Error {
     #[default]
     DefErr,
 
     ParserErr(String),
     LexerErr(String),
+    IllegalByte {
+        byte: u8,
+        offset: usize,
+        line: usize,
+        col: usize,
+    },
+    /// a file read failed, holds the message of the underlying
+    /// `io::Error` so the variant stays comparable and clonable
+    Io(String),
+}
+
+/// `source()` is always `None`, every variant is a leaf error, the impl
+/// is what lets `?` hand these to `anyhow`/`thiserror` callers
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(format!("{}", err))
+    }
+}
+
+impl From<core::str::Utf8Error> for Error {
+    fn from(err: core::str::Utf8Error) -> Self {
+        Error::LexerErr(format!("invalid utf-8: {}", err))
+    }
 }
 impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let text: String;
         match self {
             Self::DefErr => text = "Deafult error".into(),
             Self::ParserErr(s) => text = format!("Parser Err {}", s),
             Self::LexerErr(s) => text = format!("Parser Err {}", s),
+            Self::IllegalByte {
+                byte, line, col, ..
+            } => text = format!("illegal byte {:#04x} at line {}, col {}", byte, line, col),
+            Self::Io(s) => text = format!("io error {}", s),
         }
         write!(f, "md-to-tui error:  {}", text)
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::Error;
+
+    #[test]
+    fn propagates_into_anyhow() {
+        fn lex() -> anyhow::Result<()> {
+            // the lenient default absorbs stray bytes, only the strict
+            // configuration still errors
+            let mut lexer = crate::parser::lexer::Lexer::with_config(
+                crate::parser::lexer::LexerConfig {
+                    strict: true,
+                    ..crate::parser::lexer::LexerConfig::default()
+                },
+            );
+            lexer.parse("\u{1}")?;
+            Ok(())
+        }
+
+        let err = lex().unwrap_err();
+        assert!(err.downcast_ref::<Error>().is_some());
+    }
+}
@@ -15,27 +15,149 @@
 //// "
 //// let res = md.parse_markdown(Some(style))
 //// `
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
 use error::Error;
+#[cfg(feature = "std")]
 use parser::{lexer::Lexer, parser::Parser};
+#[cfg(feature = "std")]
 use ratatui::text::Text;
-use style::style::MdStyle;
-mod error;
-mod parser;
+#[cfg(feature = "std")]
+use style::style::{MdStyle, Theme};
+pub mod error;
+pub mod parser;
+#[cfg(feature = "std")]
+pub mod render;
+#[cfg(feature = "std")]
 pub mod style;
 
+/// parse `input` as markdown and render it, `None` uses the default
+/// `Theme`, the one-stop entry point for the common case
+///
+/// ```rust
+/// let text = md_to_tui::md_to_tui("# Hello", None).unwrap();
+/// assert_eq!(text.lines.len(), 1);
+/// ```
+#[cfg(feature = "std")]
+pub fn md_to_tui(input: &str, theme: Option<&Theme>) -> Result<Text<'static>, Error> {
+    let mut lexer = Lexer::new();
+    let tokens = lexer.parse(input)?;
+
+    let mut parser = parser::ast::Parser::new(tokens);
+    let nodes = parser.parse()?;
+
+    Ok(render::render::to_text(&nodes, theme))
+}
+
+/// like `md_to_tui` but word-wraps the output to `width` columns so it
+/// fits a fixed-size widget, a `width` of zero disables wrapping
+#[cfg(feature = "std")]
+pub fn md_to_tui_wrapped(input: &str, width: u16) -> Result<Text<'static>, Error> {
+    let mut lexer = Lexer::new();
+    let tokens = lexer.parse(input)?;
+
+    let mut parser = parser::ast::Parser::new(tokens);
+    let nodes = parser.parse()?;
+
+    Ok(render::render::to_text_wrapped(&nodes, None, width))
+}
+
+/// builder over the whole lex → parse → render pipeline, composes the
+/// configuration the individual stages accept into one entry point
+///
+/// ```rust
+/// use md_to_tui::style::style::Theme;
+/// use md_to_tui::MdToTui;
+///
+/// let theme = Theme { rule_width: 40, ..Theme::default() };
+/// let text = MdToTui::new()
+///     .theme(theme)
+///     .width(80)
+///     .render("# Hello")
+///     .unwrap();
+/// assert_eq!(text.lines.len(), 1);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone)]
+pub struct MdToTui {
+    theme: Option<Theme>,
+    width: u16,
+    tab_width: Option<usize>,
+    lexer_config: parser::lexer::LexerConfig,
+}
+
+#[cfg(feature = "std")]
+impl MdToTui {
+    pub fn new() -> MdToTui {
+        MdToTui::default()
+    }
+
+    /// the `Theme` rendered output is styled with, defaults to
+    /// `Theme::default`
+    pub fn theme(mut self, theme: Theme) -> MdToTui {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// word-wrap the output to `width` columns, zero (the default)
+    /// disables wrapping
+    pub fn width(mut self, width: u16) -> MdToTui {
+        self.width = width;
+        self
+    }
+
+    /// the number of columns a tab advances during parsing
+    pub fn tab_width(mut self, width: usize) -> MdToTui {
+        self.tab_width = Some(width);
+        self
+    }
+
+    /// extra word characters merged into the lexer's indent runs
+    pub fn lexer_config(mut self, config: parser::lexer::LexerConfig) -> MdToTui {
+        self.lexer_config = config;
+        self
+    }
+
+    /// run the configured pipeline over `input`
+    pub fn render(&self, input: &str) -> Result<Text<'static>, Error> {
+        let mut lexer = Lexer::with_config(self.lexer_config.clone());
+        let tokens = lexer.parse(input)?;
+
+        let mut parser = parser::ast::Parser::new(tokens);
+        if let Some(width) = self.tab_width {
+            parser.set_tab_width(width);
+        }
+        let nodes = parser.parse()?;
+
+        Ok(render::render::to_text_wrapped(
+            &nodes,
+            self.theme.as_ref(),
+            self.width,
+        ))
+    }
+}
+
 /// trait MarkdownParsable will take any trait that impl `ToString` and parse it into ratatui Text
+#[cfg(feature = "std")]
 pub trait MarkdownParsable {
     /// Convert type to Text
     fn parse_markdown(&self, style: Option<MdStyle>) -> Result<Text<'static>, Error>;
 }
 
+#[cfg(feature = "std")]
 impl<T> MarkdownParsable for T
 where
     T: ToString,
 {
     fn parse_markdown(&self, style: Option<MdStyle>) -> Result<Text<'static>, Error> {
+        // the lexed tokens borrow `input`, everything they feed into is
+        // owned before this frame returns
+        let input = self.to_string();
         let mut lexer = Lexer::new();
-        let res = lexer.parse(self)?;
+        let res = lexer.parse(&input)?;
 
         let mut parser = Parser::new(res, style);
         let res = parser.parse()?;
@@ -44,7 +166,7 @@ where
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
     use std::{
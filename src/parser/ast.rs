@@ -0,0 +1,4312 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::ops::Range;
+
+use crate::error::Error;
+
+use super::lexer::{Span, Spanned, Token};
+
+/// block level element of a markdown document
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Node {
+    Heading {
+        level: usize,
+        inline: Vec<Inline>,
+        /// github-style anchor, lowercased with spaces as hyphens and
+        /// punctuation stripped, repeated headings take `-1`, `-2`...
+        /// suffixes so intra-document links stay unambiguous
+        slug: String,
+    },
+    Paragraph(Vec<Inline>),
+    List {
+        ordered: bool,
+        items: Vec<ListItem>,
+        /// `false` when blank lines separated the items (or an item
+        /// held more than one paragraph), loose lists render with
+        /// extra spacing between items
+        tight: bool,
+    },
+    CodeBlock {
+        lang: Option<String>,
+        body: String,
+    },
+    Table {
+        align: Vec<Align>,
+        header: Vec<Vec<Inline>>,
+        rows: Vec<Vec<Vec<Inline>>>,
+    },
+    BlockQuote(Vec<Node>),
+    /// a blockquote whose first line was a `[!KIND]` marker, the
+    /// github/obsidian callout form, `title` is the kind's display name
+    Callout {
+        kind: CalloutKind,
+        title: String,
+        children: Vec<Node>,
+    },
+    /// `term` / `: definition` entries, each term pairs with one or
+    /// more definitions
+    DefinitionList(Vec<(Vec<Inline>, Vec<Vec<Inline>>)>),
+    /// the collected footnote definitions, appended after the last
+    /// block in the order they were first referenced, each entry pairs
+    /// the assigned label with the definition content
+    FootnoteDefs(Vec<(String, Vec<Inline>)>),
+    /// a heading together with the content below it, only produced
+    /// under `ParseConfig::sections` so interactive viewers can fold by
+    /// heading, `collapsed` starts out `false`
+    Section {
+        level: usize,
+        heading: Vec<Inline>,
+        /// the anchor of the heading that opened the section
+        slug: String,
+        children: Vec<Node>,
+        collapsed: bool,
+    },
+    /// a block of raw html, stored uninterpreted, the renderer decides
+    /// whether to show or strip it
+    Html(String),
+    /// `$$...$$` display math, the raw latex without the delimiters
+    MathBlock(String),
+    Rule,
+}
+
+impl Node {
+    /// the level of a `Node::Heading` or `Node::Section`, `None` for
+    /// every other block, saves callers a pattern-match when comparing
+    /// heading depths
+    pub fn heading_level(&self) -> Option<usize> {
+        match self {
+            Node::Heading { level, .. } | Node::Section { level, .. } => Some(*level),
+            _ => None,
+        }
+    }
+}
+
+/// a parsed block together with the byte range of the source that
+/// produced it, when known
+pub type SpannedNode = (Node, Option<Range<usize>>);
+
+/// the recognized callout kinds, an unknown `[!KIND]` label falls back
+/// to `Generic` so it still renders as a callout
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CalloutKind {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+    Generic,
+}
+
+impl CalloutKind {
+    /// the kind a `[!label]` marker names, matched case-insensitively
+    pub fn from_label(label: &str) -> CalloutKind {
+        match label.to_lowercase().as_str() {
+            "note" => CalloutKind::Note,
+            "tip" => CalloutKind::Tip,
+            "important" => CalloutKind::Important,
+            "warning" => CalloutKind::Warning,
+            "caution" => CalloutKind::Caution,
+            _ => CalloutKind::Generic,
+        }
+    }
+}
+
+/// column alignment from a table delimiter row, `:--` is left, `--:` is
+/// right and `:-:` is center
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// one list entry, nested sublists live in `children`
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ListItem {
+    pub inline: Vec<Inline>,
+    pub children: Vec<Node>,
+    /// `Some` when the item opens with a `[ ]`/`[x]` task marker
+    pub checked: Option<bool>,
+}
+
+/// inline element inside a block
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Inline {
+    Text(String),
+    SoftBreak,
+    HardBreak,
+    Bold(Vec<Inline>),
+    Italic(Vec<Inline>),
+    Code(String),
+    Link {
+        text: Vec<Inline>,
+        href: String,
+        title: Option<String>,
+        /// for an `#anchor` href, the index of the heading whose slug
+        /// matched, resolved after the whole document has parsed so
+        /// forward links work, `None` for external links
+        anchor: Option<NodeId>,
+    },
+    /// `@name` mention, the `@` is not part of the stored name
+    Mention(String),
+    /// `[^id]` footnote reference, the stored string is the label the
+    /// footnote was numbered with, assigned sequentially by first
+    /// reference
+    FootnoteRef(String),
+    /// `![alt](src)` image, terminals render a placeholder glyph plus
+    /// the alt text, `src` is kept for detail views
+    Image {
+        alt: String,
+        src: String,
+    },
+    /// `^text^` superscript, rendered with unicode superscript forms
+    /// where they exist
+    Superscript(Vec<Inline>),
+    /// `~text~` subscript, rendered with unicode subscript forms where
+    /// they exist
+    Subscript(Vec<Inline>),
+    /// a raw inline html tag like `<b>`, stored uninterpreted including
+    /// the angle brackets
+    Html(String),
+    /// `$...$` inline math, the raw latex without the delimiters
+    Math(String),
+}
+
+/// parser behavior switches, the default is a GFM-like profile with
+/// every extension the parser implements enabled, turning one off
+/// leaves its syntax as literal text for strict-CommonMark consumers
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseConfig {
+    /// group content under its heading into `Node::Section` so a TUI
+    /// can fold and unfold by heading, changes the tree shape (opt-in)
+    pub sections: bool,
+    /// `|`-delimited tables with a delimiter row
+    pub tables: bool,
+    /// `[ ]`/`[x]` task markers on list items
+    pub task_lists: bool,
+    /// `<https://...>` and `<user@host>` autolinks
+    pub autolinks: bool,
+    /// `[^id]` references and `[^id]: text` definitions
+    pub footnotes: bool,
+}
+
+impl Default for ParseConfig {
+    fn default() -> Self {
+        ParseConfig {
+            sections: false,
+            tables: true,
+            task_lists: true,
+            autolinks: true,
+            footnotes: true,
+        }
+    }
+}
+
+/// the metadata block at the very start of a document, delimited by
+/// `---` (yaml) or `+++` (toml) lines, the body is kept raw so callers
+/// can hand it to whatever deserializer matches `format`
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Frontmatter {
+    pub format: FrontmatterFormat,
+    pub raw: String,
+}
+
+/// which delimiter a `Frontmatter` block was fenced with
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrontmatterFormat {
+    Yaml,
+    Toml,
+}
+
+/// the index of a top-level node in the parsed output, the unit
+/// `Inline::Link::anchor` counts in, assigned before any section
+/// folding so it indexes the flat block list
+pub type NodeId = usize;
+
+/// a recoverable oddity noticed during parsing, collected by
+/// `parse_with_warnings`, `span` is the byte range of the offending
+/// source when the parser was built with `new_spanned`
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Warning {
+    pub span: Option<Range<usize>>,
+    pub message: String,
+}
+
+/// serialize parsed nodes as JSON, handy for golden-file snapshots and
+/// non-Rust tooling
+#[cfg(feature = "serde")]
+pub fn to_json(nodes: &[Node]) -> String {
+    serde_json::to_string(nodes).expect("serializing the AST cannot fail")
+}
+
+/// render nodes as an indented tree for eyeballing in test failures,
+/// one element per line with children two spaces deeper, distinct from
+/// the `serde` JSON output which is meant for machines
+pub fn debug_tree(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        write_node(node, 0, &mut out);
+    }
+    out
+}
+
+fn push_line(out: &mut String, depth: usize, text: &str) {
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+    out.push_str(text);
+    out.push('\n');
+}
+
+fn write_node(node: &Node, depth: usize, out: &mut String) {
+    match node {
+        Node::Heading { level, inline, .. } => {
+            push_line(out, depth, &format!("Heading({level})"));
+            write_inline(inline, depth + 1, out);
+        }
+        Node::Paragraph(inline) => {
+            push_line(out, depth, "Paragraph");
+            write_inline(inline, depth + 1, out);
+        }
+        Node::List { ordered, items, .. } => {
+            let kind = if *ordered { "ordered" } else { "unordered" };
+            push_line(out, depth, &format!("List({kind})"));
+            for item in items {
+                push_line(out, depth + 1, "Item");
+                write_inline(&item.inline, depth + 2, out);
+                for child in &item.children {
+                    write_node(child, depth + 2, out);
+                }
+            }
+        }
+        Node::CodeBlock { lang, body } => {
+            push_line(out, depth, &format!("CodeBlock({:?})", lang.as_deref()));
+            for line in body.lines() {
+                push_line(out, depth + 1, line);
+            }
+        }
+        Node::Table { header, rows, .. } => {
+            push_line(out, depth, "Table");
+            push_line(out, depth + 1, "Header");
+            for cell in header {
+                write_inline(cell, depth + 2, out);
+            }
+            for row in rows {
+                push_line(out, depth + 1, "Row");
+                for cell in row {
+                    write_inline(cell, depth + 2, out);
+                }
+            }
+        }
+        Node::BlockQuote(inner) => {
+            push_line(out, depth, "BlockQuote");
+            for child in inner {
+                write_node(child, depth + 1, out);
+            }
+        }
+        Node::Callout {
+            kind,
+            title,
+            children,
+        } => {
+            push_line(out, depth, &format!("Callout({kind:?}, {title:?})"));
+            for child in children {
+                write_node(child, depth + 1, out);
+            }
+        }
+        Node::DefinitionList(entries) => {
+            push_line(out, depth, "DefinitionList");
+            for (term, defs) in entries {
+                push_line(out, depth + 1, "Term");
+                write_inline(term, depth + 2, out);
+                for def in defs {
+                    push_line(out, depth + 1, "Definition");
+                    write_inline(def, depth + 2, out);
+                }
+            }
+        }
+        Node::FootnoteDefs(defs) => {
+            push_line(out, depth, "FootnoteDefs");
+            for (label, inline) in defs {
+                push_line(out, depth + 1, &format!("[{label}]"));
+                write_inline(inline, depth + 2, out);
+            }
+        }
+        Node::Section {
+            level,
+            heading,
+            children,
+            collapsed,
+            ..
+        } => {
+            let state = if *collapsed { ", collapsed" } else { "" };
+            push_line(out, depth, &format!("Section({level}{state})"));
+            write_inline(heading, depth + 1, out);
+            for child in children {
+                write_node(child, depth + 1, out);
+            }
+        }
+        Node::Html(raw) => push_line(out, depth, &format!("Html({raw:?})")),
+        Node::MathBlock(body) => push_line(out, depth, &format!("MathBlock({body:?})")),
+        Node::Rule => push_line(out, depth, "Rule"),
+    }
+}
+
+fn write_inline(inline: &[Inline], depth: usize, out: &mut String) {
+    for node in inline {
+        match node {
+            Inline::Text(text) => push_line(out, depth, &format!("Text({text:?})")),
+            Inline::SoftBreak => push_line(out, depth, "SoftBreak"),
+            Inline::HardBreak => push_line(out, depth, "HardBreak"),
+            Inline::Bold(inner) => {
+                push_line(out, depth, "Bold");
+                write_inline(inner, depth + 1, out);
+            }
+            Inline::Italic(inner) => {
+                push_line(out, depth, "Italic");
+                write_inline(inner, depth + 1, out);
+            }
+            Inline::Code(code) => push_line(out, depth, &format!("Code({code:?})")),
+            Inline::Link { text, href, .. } => {
+                push_line(out, depth, &format!("Link({href})"));
+                write_inline(text, depth + 1, out);
+            }
+            Inline::Mention(name) => push_line(out, depth, &format!("Mention({name})")),
+            Inline::FootnoteRef(label) => {
+                push_line(out, depth, &format!("FootnoteRef({label})"))
+            }
+            Inline::Image { alt, src } => {
+                push_line(out, depth, &format!("Image({alt:?}, {src})"))
+            }
+            Inline::Superscript(inner) => {
+                push_line(out, depth, "Superscript");
+                write_inline(inner, depth + 1, out);
+            }
+            Inline::Subscript(inner) => {
+                push_line(out, depth, "Subscript");
+                write_inline(inner, depth + 1, out);
+            }
+            Inline::Html(raw) => push_line(out, depth, &format!("Html({raw:?})")),
+            Inline::Math(body) => push_line(out, depth, &format!("Math({body:?})")),
+        }
+    }
+}
+
+/// build a nested table-of-contents list from the headings in
+/// `nodes`, each entry links to the heading's `#slug` anchor and
+/// deeper headings nest as child lists, headings outside
+/// `min_level..=max_level` are skipped
+pub fn table_of_contents(nodes: &[Node], min_level: usize, max_level: usize) -> Node {
+    fn gather(nodes: &[Node], min: usize, max: usize, out: &mut Vec<(usize, Vec<Inline>, String)>) {
+        for node in nodes {
+            match node {
+                Node::Heading {
+                    level,
+                    inline,
+                    slug,
+                } if (min..=max).contains(level) => {
+                    out.push((*level, inline.clone(), slug.clone()));
+                }
+                Node::Section {
+                    level,
+                    heading,
+                    slug,
+                    children,
+                    ..
+                } => {
+                    if (min..=max).contains(level) {
+                        out.push((*level, heading.clone(), slug.clone()));
+                    }
+                    gather(children, min, max, out);
+                }
+                Node::BlockQuote(inner) => gather(inner, min, max, out),
+                Node::Callout { children, .. } => gather(children, min, max, out),
+                _ => {}
+            }
+        }
+    }
+
+    fn build(entries: &[(usize, Vec<Inline>, String)], at: &mut usize, level: usize) -> Node {
+        let mut items = Vec::new();
+        while *at < entries.len() {
+            let (entry_level, inline, slug) = &entries[*at];
+            if *entry_level < level {
+                break;
+            }
+            *at += 1;
+            let link = Inline::Link {
+                text: inline.clone(),
+                href: format!("#{slug}"),
+                title: None,
+                anchor: None,
+            };
+            let mut children = Vec::new();
+            if entries.get(*at).is_some_and(|(next, _, _)| *next > *entry_level) {
+                children.push(build(entries, at, *entry_level + 1));
+            }
+            items.push(ListItem {
+                inline: vec![link],
+                children,
+                checked: None,
+            });
+        }
+        Node::List {
+            ordered: false,
+            items,
+            tight: true,
+        }
+    }
+
+    let mut entries = Vec::new();
+    gather(nodes, min_level, max_level, &mut entries);
+    let mut at = 0;
+    build(&entries, &mut at, 0)
+}
+
+/// group a flat node list into nested `Node::Section`s, a heading opens
+/// a section holding everything until the next heading of the same or a
+/// shallower level, deeper headings nest
+fn fold_sections(flat: Vec<SpannedNode>) -> Vec<SpannedNode> {
+    struct Open {
+        level: usize,
+        heading: Vec<Inline>,
+        slug: String,
+        children: Vec<Node>,
+        span: Option<Range<usize>>,
+    }
+
+    fn close_one(stack: &mut Vec<Open>, out: &mut Vec<SpannedNode>) {
+        let Some(open) = stack.pop() else { return };
+        let node = Node::Section {
+            level: open.level,
+            heading: open.heading,
+            slug: open.slug,
+            children: open.children,
+            collapsed: false,
+        };
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => out.push((node, open.span)),
+        }
+    }
+
+    let mut out: Vec<SpannedNode> = Vec::new();
+    let mut stack: Vec<Open> = Vec::new();
+    for (node, span) in flat {
+        if let Node::Heading {
+            level,
+            inline,
+            slug,
+        } = node
+        {
+            while stack.last().is_some_and(|open| open.level >= level) {
+                close_one(&mut stack, &mut out);
+            }
+            stack.push(Open {
+                level,
+                heading: inline,
+                slug,
+                children: Vec::new(),
+                span,
+            });
+        } else {
+            match stack.last_mut() {
+                Some(open) => open.children.push(node),
+                None => out.push((node, span)),
+            }
+        }
+    }
+    while !stack.is_empty() {
+        close_one(&mut stack, &mut out);
+    }
+    out
+}
+
+/// parses a lexed token stream into a list of `Node`, the tokens borrow
+/// the source for `'a` but the produced nodes own their text
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Parser<'a> {
+    input: Vec<Token<'a>>,
+    /// the source span of every token in `input`, empty when the tokens
+    /// were lexed without spans
+    spans: Vec<Span>,
+    position: usize,
+    definitions: BTreeMap<String, String>,
+    tab_width: usize,
+    config: ParseConfig,
+    warnings: Vec<Warning>,
+    footnote_defs: BTreeMap<String, Vec<Inline>>,
+    footnote_order: Vec<String>,
+    /// per-base counts backing heading slug de-duplication
+    slug_counts: BTreeMap<String, usize>,
+    frontmatter: Option<Frontmatter>,
+    max_depth: usize,
+    depth: usize,
+    depth_warned: bool,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(input: Vec<Token<'a>>) -> Parser<'a> {
+        Parser {
+            input,
+            spans: Vec::new(),
+            position: 0,
+            definitions: BTreeMap::new(),
+            tab_width: 4,
+            config: ParseConfig::default(),
+            warnings: Vec::new(),
+            footnote_defs: BTreeMap::new(),
+            footnote_order: Vec::new(),
+            slug_counts: BTreeMap::new(),
+            frontmatter: None,
+            max_depth: 100,
+            depth: 0,
+            depth_warned: false,
+        }
+    }
+
+    /// like `new` but from the output of `Lexer::parse_spanned`, blocks
+    /// from `parse_spanned` can then report their source byte ranges
+    pub fn new_spanned(input: Vec<Spanned<Token<'a>>>) -> Parser<'a> {
+        let mut tokens: Vec<Token<'a>> = Vec::with_capacity(input.len());
+        let mut spans: Vec<Span> = Vec::with_capacity(input.len());
+        for sp in input {
+            tokens.push(sp.token);
+            spans.push(sp.span);
+        }
+        Parser {
+            input: tokens,
+            spans,
+            position: 0,
+            definitions: BTreeMap::new(),
+            tab_width: 4,
+            config: ParseConfig::default(),
+            warnings: Vec::new(),
+            footnote_defs: BTreeMap::new(),
+            footnote_order: Vec::new(),
+            slug_counts: BTreeMap::new(),
+            frontmatter: None,
+            max_depth: 100,
+            depth: 0,
+            depth_warned: false,
+        }
+    }
+
+    /// set the number of columns a tab advances indentation by, each tab
+    /// expands to the next multiple of `width` (default 4)
+    pub fn set_tab_width(&mut self, width: usize) {
+        self.tab_width = width;
+    }
+
+    /// cap the blockquote/list nesting the parser recurses into
+    /// (default 100), content past the cap is kept as literal text with
+    /// a warning so untrusted input cannot overflow the stack
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
+    }
+
+    /// switch on the opt-in behaviors in `config`
+    pub fn set_config(&mut self, config: ParseConfig) {
+        self.config = config;
+    }
+
+    /// warn (once) that `max_depth` was exceeded
+    fn depth_warning(&mut self) {
+        if self.depth_warned {
+            return;
+        }
+        self.depth_warned = true;
+        self.warnings.push(Warning {
+            span: None,
+            message: format!("maximum nesting depth {} exceeded", self.max_depth),
+        });
+    }
+
+    /// expand a tab at column `indent` to the next tab stop
+    fn next_tab_stop(&self, indent: usize) -> usize {
+        let width = self.tab_width.max(1);
+        (indent / width + 1) * width
+    }
+
+    pub fn parse(&mut self) -> Result<Vec<Node>, Error> {
+        Ok(self
+            .parse_spanned()?
+            .into_iter()
+            .map(|(node, _)| node)
+            .collect())
+    }
+
+    /// like `parse` but also returns the recoverable oddities noticed
+    /// along the way (dangling reference links, ragged table rows)
+    pub fn parse_with_warnings(&mut self) -> Result<(Vec<Node>, Vec<Warning>), Error> {
+        let nodes = self.parse()?;
+        Ok((nodes, core::mem::take(&mut self.warnings)))
+    }
+
+    /// like `parse` but also returns the frontmatter block when the
+    /// document opened with one, `None` otherwise
+    pub fn parse_with_frontmatter(&mut self) -> Result<(Option<Frontmatter>, Vec<Node>), Error> {
+        let nodes = self.parse()?;
+        Ok((self.frontmatter.take(), nodes))
+    }
+
+    /// like `parse` but each block comes with the byte range of the
+    /// source that produced it, the range is only known when the parser
+    /// was built with `new_spanned`
+    pub fn parse_spanned(&mut self) -> Result<Vec<SpannedNode>, Error> {
+        self.extract_frontmatter();
+        self.collect_definitions()?;
+        let mut nodes: Vec<SpannedNode> = Vec::new();
+        while !self.at_end() {
+            let start = self.position;
+            let node = match self.current() {
+                Token::SoftBreak | Token::HardBreak => {
+                    self.bump();
+                    None
+                }
+                // a line of only spaces and tabs is blank, it separates
+                // blocks but produces nothing
+                Token::WhiteSpace | Token::Tab if self.blank_line(self.position) => {
+                    self.position = self.line_end(self.position);
+                    None
+                }
+                Token::Heading(level) => Some(self.parse_heading(level)?),
+                Token::CodeBlock { lang, body } => {
+                    self.bump();
+                    Some(Node::CodeBlock {
+                        lang: lang.as_deref().map(str::to_string),
+                        body: body.to_string(),
+                    })
+                }
+                // a rule only counts when the run is alone on its line,
+                // `***x***` must stay inline emphasis
+                Token::Rule(_, n) if n >= 3 && self.line_is_only_rule() => {
+                    self.bump();
+                    Some(Node::Rule)
+                }
+                // `- - -` style spaced rules and `___` runs, checked
+                // before list markers so `- - -` does not open a list
+                Token::Dash | Token::Asterisk | Token::Undersocre | Token::WhiteSpace
+                    if self.thematic_break(self.position).is_some() =>
+                {
+                    let width = self.thematic_break(self.position).unwrap();
+                    for _ in 0..width {
+                        self.bump();
+                    }
+                    Some(Node::Rule)
+                }
+                Token::Dash
+                | Token::Asterisk
+                | Token::Plus
+                | Token::OrderedMarker(_)
+                | Token::WhiteSpace
+                | Token::Tab
+                    if self.list_marker(self.position).is_some() =>
+                {
+                    let (indent, _, _) = self.list_marker(self.position).unwrap();
+                    Some(self.parse_list(indent)?)
+                }
+                Token::BlockQuote => Some(self.parse_blockquote()?),
+                // a `<tag>` opening a line starts a raw html block
+                Token::LeftAngle if self.html_block_start(self.position) => {
+                    Some(self.parse_html_block()?)
+                }
+                // `$$` opens display math
+                Token::Dollar if self.input.get(self.position + 1) == Some(&Token::Dollar) => {
+                    Some(self.parse_math_block()?)
+                }
+                // a term line directly above `: definition` lines opens
+                // a definition list
+                _ if self.definition_follows(self.position) => {
+                    Some(self.parse_definition_list()?)
+                }
+                // four columns of leading indent open a code block, an
+                // indented line inside a list is handled by the list arm
+                Token::WhiteSpace | Token::Tab if self.indented_code_start(self.position) => {
+                    Some(self.parse_indented_code()?)
+                }
+                // a `|` header row only opens a table when the next line
+                // is a delimiter row
+                Token::Pipe
+                    if self.config.tables
+                        && self
+                            .table_align(self.line_end(self.position) + 1)
+                            .is_some() =>
+                {
+                    let align = self
+                        .table_align(self.line_end(self.position) + 1)
+                        .unwrap();
+                    Some(self.parse_table(align)?)
+                }
+                _ => {
+                    let node = self.parse_paragraph()?;
+                    // a following line of only `=` or `-` turns the
+                    // paragraph into a setext heading
+                    if let (Some(level), Node::Paragraph(inline)) = (self.setext_level(), &node) {
+                        self.bump();
+                        self.bump();
+                        let inline = inline.clone();
+                        let slug = self.slug_for(&inline);
+                        Some(Node::Heading {
+                            level,
+                            inline,
+                            slug,
+                        })
+                    } else {
+                        Some(node)
+                    }
+                }
+            };
+            if let Some(node) = node {
+                let span = self.source_span(start, self.position);
+                nodes.push((node, span));
+            }
+        }
+        if !self.footnote_order.is_empty() {
+            let defs = self
+                .footnote_order
+                .iter()
+                .enumerate()
+                .map(|(i, id)| {
+                    let inline = self.footnote_defs.get(id).cloned().unwrap_or_default();
+                    ((i + 1).to_string(), inline)
+                })
+                .collect();
+            nodes.push((Node::FootnoteDefs(defs), None));
+        }
+        for id in self.footnote_defs.keys() {
+            if !self.footnote_order.contains(id) {
+                self.warnings.push(Warning {
+                    span: None,
+                    message: format!("unreferenced footnote definition `{id}`"),
+                });
+            }
+        }
+        self.resolve_anchors(&mut nodes);
+        if self.config.sections {
+            nodes = fold_sections(nodes);
+        }
+        Ok(nodes)
+    }
+
+    /// pull a `---`/`+++` fenced frontmatter block off the very start
+    /// of the input, an opener without a matching closing line is left
+    /// alone so a document may still begin with a thematic break
+    fn extract_frontmatter(&mut self) {
+        let format = match self.input.first() {
+            Some(Token::Rule('-', 3)) => FrontmatterFormat::Yaml,
+            Some(Token::Plus)
+                if self.input.get(1) == Some(&Token::Plus)
+                    && self.input.get(2) == Some(&Token::Plus) =>
+            {
+                FrontmatterFormat::Toml
+            }
+            _ => return,
+        };
+        let open_len = match format {
+            FrontmatterFormat::Yaml => 1,
+            FrontmatterFormat::Toml => 3,
+        };
+        // the opener must be alone on its line
+        if self.line_end(0) != open_len {
+            return;
+        }
+        let mut i = open_len + 1;
+        let close_start = loop {
+            if i >= self.input.len() {
+                return;
+            }
+            let end = self.line_end(i);
+            let closes = match format {
+                FrontmatterFormat::Yaml => self.input[i..end] == [Token::Rule('-', 3)],
+                FrontmatterFormat::Toml => {
+                    self.input[i..end] == [Token::Plus, Token::Plus, Token::Plus]
+                }
+            };
+            if closes {
+                break i;
+            }
+            i = end + 1;
+        };
+        let mut raw = String::new();
+        for tk in &self.input[open_len + 1..close_start] {
+            match tk {
+                Token::SoftBreak | Token::HardBreak => raw.push('\n'),
+                tk => raw.push_str(&Self::token_literal(tk)),
+            }
+        }
+        while raw.ends_with('\n') {
+            raw.pop();
+        }
+        // drop the block and its closing line break from the input
+        let drained = (self.line_end(close_start) + 1).min(self.input.len());
+        self.input.drain(..drained);
+        if !self.spans.is_empty() {
+            self.spans.drain(..drained.min(self.spans.len()));
+        }
+        self.frontmatter = Some(Frontmatter { format, raw });
+    }
+
+    /// whether the line at `at` holds only spaces and tabs
+    fn blank_line(&self, at: usize) -> bool {
+        self.input[at..self.line_end(at).min(self.input.len())]
+            .iter()
+            .all(|tk| matches!(tk, Token::WhiteSpace | Token::Tab))
+    }
+
+    /// the byte range covered by the tokens in `[start, end)`, `None`
+    /// when the tokens were lexed without spans
+    fn source_span(&self, start: usize, end: usize) -> Option<Range<usize>> {
+        let first = self.spans.get(start)?;
+        let last = self.spans.get(end.checked_sub(1)?)?;
+        Some(first.start..last.end)
+    }
+
+    fn parse_heading(&mut self, level: usize) -> Result<Node, Error> {
+        self.bump();
+        // a single space between the `#` run and the text is part of the
+        // marker, not the heading text
+        if self.current() == Token::WhiteSpace {
+            self.bump();
+        }
+        let inline = self.parse_inline_until_break()?;
+        let slug = self.slug_for(&inline);
+        Ok(Node::Heading {
+            level,
+            inline,
+            slug,
+        })
+    }
+
+    /// the indent width, token count of the marker (indent + marker +
+    /// trailing space) and orderedness when the line at `pos` opens a
+    /// list item
+    fn list_marker(&self, pos: usize) -> Option<(usize, usize, bool)> {
+        let mut indent = 0;
+        let mut i = pos;
+        loop {
+            match self.input.get(i)? {
+                Token::WhiteSpace => indent += 1,
+                Token::Tab => indent = self.next_tab_stop(indent),
+                _ => break,
+            }
+            i += 1;
+        }
+        let ordered = match self.input.get(i)? {
+            Token::Dash | Token::Asterisk | Token::Plus => false,
+            Token::OrderedMarker(_) => true,
+            _ => return None,
+        };
+        // the marker must be followed by a space to count as a list item
+        if !matches!(self.input.get(i + 1), Some(Token::WhiteSpace)) {
+            return None;
+        }
+        Some((indent, i + 2 - pos, ordered))
+    }
+
+    /// parse consecutive list item lines at `level_indent`, deeper items
+    /// become sublists of the item above them
+    fn parse_list(&mut self, level_indent: usize) -> Result<Node, Error> {
+        if self.depth >= self.max_depth {
+            self.depth_warning();
+            return Ok(Node::Paragraph(self.parse_inline_until_break()?));
+        }
+        let mut items: Vec<ListItem> = Vec::new();
+        let mut ordered = false;
+        let mut tight = true;
+
+        loop {
+            match self.list_marker(self.position) {
+                Some((indent, _, _)) if indent > level_indent => {
+                    self.depth += 1;
+                    let child = self.parse_list(indent)?;
+                    self.depth -= 1;
+                    match items.last_mut() {
+                        Some(last) => last.children.push(child),
+                        // a sublist with no parent item still needs a home
+                        None => items.push(ListItem {
+                            inline: Vec::new(),
+                            children: vec![child],
+                            checked: None,
+                        }),
+                    }
+                    // the sublist consumed its own trailing break
+                    continue;
+                }
+                Some((indent, width, item_ordered)) if indent == level_indent => {
+                    if items.is_empty() {
+                        ordered = item_ordered;
+                    } else if item_ordered != ordered {
+                        // switching marker kinds at the same indent
+                        // starts a new list instead of continuing this one
+                        break;
+                    }
+                    for _ in 0..width {
+                        self.bump();
+                    }
+                    let checked = self.task_marker();
+                    if checked.is_some() {
+                        self.position += 3;
+                        if self.current() == Token::WhiteSpace {
+                            self.bump();
+                        }
+                    }
+                    let inline = self.parse_inline_until_break()?;
+                    items.push(ListItem {
+                        inline,
+                        children: Vec::new(),
+                        checked,
+                    });
+                }
+                // a shallower item belongs to an enclosing list
+                Some(_) => break,
+                None => {
+                    // an indented line without a marker wraps the item above
+                    let folded = match items.last_mut() {
+                        Some(last) => self.fold_continuation(level_indent, &mut last.inline)?,
+                        None => false,
+                    };
+                    if !folded {
+                        break;
+                    }
+                }
+            }
+            let mut done = false;
+            loop {
+                match self.current() {
+                    Token::SoftBreak => {
+                        self.bump();
+                        break;
+                    }
+                    Token::HardBreak => {
+                        // the blank line may span several break tokens
+                        let mut next = self.position + 1;
+                        while matches!(
+                            self.input.get(next),
+                            Some(Token::SoftBreak | Token::HardBreak)
+                        ) {
+                            next += 1;
+                        }
+                        // a blank line before another marker at this
+                        // indent makes the list loose instead of
+                        // ending it
+                        if matches!(
+                            self.list_marker(next),
+                            Some((indent, _, item_ordered))
+                                if indent == level_indent && item_ordered == ordered
+                        ) {
+                            tight = false;
+                            self.position = next;
+                            break;
+                        }
+                        // indented content after the blank line is a
+                        // second paragraph of the item above
+                        let mut indent = 0;
+                        let mut i = next;
+                        while let Some(Token::WhiteSpace | Token::Tab) = self.input.get(i) {
+                            indent = match self.input[i] {
+                                Token::Tab => self.next_tab_stop(indent),
+                                _ => indent + 1,
+                            };
+                            i += 1;
+                        }
+                        let blank = matches!(
+                            self.input.get(i),
+                            None | Some(Token::SoftBreak | Token::HardBreak | Token::Eof)
+                        );
+                        if indent > level_indent && !blank && !items.is_empty() {
+                            self.position = i;
+                            let inline = self.parse_inline_until_break()?;
+                            if let Some(last) = items.last_mut() {
+                                last.children.push(Node::Paragraph(inline));
+                            }
+                            tight = false;
+                            continue;
+                        }
+                        done = true;
+                        break;
+                    }
+                    _ => {
+                        done = true;
+                        break;
+                    }
+                }
+            }
+            if done {
+                break;
+            }
+        }
+
+        Ok(Node::List {
+            ordered,
+            items,
+            tight,
+        })
+    }
+
+    /// the checkbox state when the item content at the current position
+    /// opens with a `[ ]`/`[x]` task marker
+    fn task_marker(&self) -> Option<bool> {
+        if !self.config.task_lists {
+            return None;
+        }
+        if self.input.get(self.position)? != &Token::LeftSquare {
+            return None;
+        }
+        let state = match self.input.get(self.position + 1)? {
+            Token::WhiteSpace => false,
+            Token::Indent(s) if s == "x" || s == "X" => true,
+            _ => return None,
+        };
+        if self.input.get(self.position + 2)? != &Token::RightSquare {
+            return None;
+        }
+        Some(state)
+    }
+
+    /// fold an indented continuation line into `item`, `false` when the
+    /// current line does not continue the list
+    fn fold_continuation(
+        &mut self,
+        level_indent: usize,
+        item: &mut Vec<Inline>,
+    ) -> Result<bool, Error> {
+        let mut indent = 0;
+        let mut i = self.position;
+        while let Some(Token::WhiteSpace | Token::Tab) = self.input.get(i) {
+            indent = match self.input[i] {
+                Token::Tab => self.next_tab_stop(indent),
+                _ => indent + 1,
+            };
+            i += 1;
+        }
+        if indent <= level_indent
+            || matches!(
+                self.input.get(i),
+                None | Some(Token::SoftBreak) | Some(Token::HardBreak) | Some(Token::Eof)
+            )
+        {
+            return Ok(false);
+        }
+        self.position = i;
+        let mut inline = self.parse_inline_until_break()?;
+        // join with a single space, merging adjacent text runs
+        match (item.last_mut(), inline.first_mut()) {
+            (Some(Inline::Text(prev)), Some(Inline::Text(next))) => {
+                prev.push(' ');
+                prev.push_str(next);
+                inline.remove(0);
+            }
+            _ => item.push(Inline::Text(" ".into())),
+        }
+        item.append(&mut inline);
+        Ok(true)
+    }
+
+    /// whether the line at `pos` is indented four or more columns and
+    /// holds any content, the opening condition for an indented code
+    /// block
+    fn indented_code_start(&self, pos: usize) -> bool {
+        let mut indent = 0;
+        let mut i = pos;
+        while let Some(Token::WhiteSpace | Token::Tab) = self.input.get(i) {
+            indent = match self.input[i] {
+                Token::Tab => self.next_tab_stop(indent),
+                _ => indent + 1,
+            };
+            i += 1;
+        }
+        indent >= 4
+            && !matches!(
+                self.input.get(i),
+                None | Some(Token::SoftBreak | Token::HardBreak | Token::Eof)
+            )
+    }
+
+    /// parse a run of four-space (or tab) indented lines as a code
+    /// block, the first four columns of indent are stripped
+    fn parse_indented_code(&mut self) -> Result<Node, Error> {
+        let mut body = String::new();
+        loop {
+            let mut indent = 0;
+            while indent < 4 && matches!(self.current(), Token::WhiteSpace | Token::Tab) {
+                indent = match self.current() {
+                    Token::Tab => self.next_tab_stop(indent),
+                    _ => indent + 1,
+                };
+                self.bump();
+            }
+            // a tab can overshoot the fourth column, the rest is content
+            for _ in 4..indent {
+                body.push(' ');
+            }
+            let end = self.line_end(self.position);
+            body.push_str(&self.literal_range(self.position, end));
+            body.push('\n');
+            self.position = end;
+            if self.current() == Token::SoftBreak && self.indented_code_start(self.position + 1) {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        Ok(Node::CodeBlock { lang: None, body })
+    }
+
+    /// whether the line at `pos` starts a `: definition` line
+    fn definition_line(&self, pos: usize) -> bool {
+        matches!(self.input.get(pos), Some(Token::Colon))
+            && matches!(self.input.get(pos + 1), Some(Token::WhiteSpace))
+    }
+
+    /// whether the line at `pos` is a term with `: definition` lines
+    /// directly below it
+    fn definition_follows(&self, pos: usize) -> bool {
+        if self.definition_line(pos) {
+            return false;
+        }
+        let end = self.line_end(pos);
+        end > pos
+            && matches!(self.input.get(end), Some(Token::SoftBreak))
+            && self.definition_line(end + 1)
+    }
+
+    /// parse consecutive `term` / `: definition` lines, a term keeps
+    /// collecting definitions until a line without the colon marker
+    fn parse_definition_list(&mut self) -> Result<Node, Error> {
+        let mut entries: Vec<(Vec<Inline>, Vec<Vec<Inline>>)> = Vec::new();
+        loop {
+            let term = self.parse_inline_until_break()?;
+            let mut defs: Vec<Vec<Inline>> = Vec::new();
+            while self.current() == Token::SoftBreak && self.definition_line(self.position + 1) {
+                // the break, the colon and its trailing space
+                self.bump();
+                self.bump();
+                self.bump();
+                defs.push(self.parse_inline_until_break()?);
+            }
+            entries.push((term, defs));
+            if self.current() == Token::SoftBreak && self.definition_follows(self.position + 1) {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        Ok(Node::DefinitionList(entries))
+    }
+
+    fn parse_paragraph(&mut self) -> Result<Node, Error> {
+        let mut inline = self.parse_inline_until_break()?;
+        // a paragraph continues over single newlines until a blank line
+        // or a block construct starts
+        while self.current() == Token::SoftBreak {
+            let next = self.input.get(self.position + 1);
+            if matches!(
+                next,
+                None | Some(Token::SoftBreak | Token::HardBreak | Token::Eof)
+            ) {
+                break;
+            }
+            if matches!(
+                next,
+                Some(Token::Heading(_) | Token::CodeBlock { .. } | Token::BlockQuote)
+            ) {
+                break;
+            }
+            if self.list_marker(self.position + 1).is_some() {
+                break;
+            }
+            if self.setext_level().is_some() {
+                break;
+            }
+            if self.thematic_break(self.position + 1).is_some() {
+                break;
+            }
+            if self.definition_line(self.position + 1) {
+                break;
+            }
+            if matches!(next, Some(Token::Rule(_, n)) if *n >= 3)
+                && matches!(
+                    self.input.get(self.position + 2),
+                    None | Some(Token::SoftBreak | Token::HardBreak | Token::Eof)
+                )
+            {
+                break;
+            }
+
+            let hard = Self::take_hard_break(&mut inline);
+            self.bump();
+            inline.push(if hard {
+                Inline::HardBreak
+            } else {
+                Inline::SoftBreak
+            });
+            let mut next_inline = self.parse_inline_until_break()?;
+            inline.append(&mut next_inline);
+        }
+        Ok(Node::Paragraph(inline))
+    }
+
+    /// strip a trailing `\` or two-space run from the end of a line,
+    /// reporting whether it marked a hard break
+    fn take_hard_break(inline: &mut Vec<Inline>) -> bool {
+        if let Some(Inline::Text(text)) = inline.last_mut() {
+            if text.ends_with('\\') {
+                text.pop();
+                if text.is_empty() {
+                    inline.pop();
+                }
+                return true;
+            }
+            if text.ends_with("  ") {
+                while text.ends_with(' ') {
+                    text.pop();
+                }
+                if text.is_empty() {
+                    inline.pop();
+                }
+                return true;
+            }
+        }
+        false
+    }
+
+    /// parse consecutive `>` prefixed lines into a quote, the stripped
+    /// body is re-parsed so a quote can hold any block content, a line
+    /// without a marker lazily continues the quote's paragraph
+    fn parse_blockquote(&mut self) -> Result<Node, Error> {
+        if self.depth >= self.max_depth {
+            self.depth_warning();
+            return Ok(Node::Paragraph(self.parse_inline_until_break()?));
+        }
+        let mut inner: Vec<Token<'a>> = Vec::new();
+        loop {
+            if self.current() == Token::BlockQuote {
+                self.bump();
+                if self.current() == Token::WhiteSpace {
+                    self.bump();
+                }
+                // `>>` nests, the extra markers are line-leading once
+                // this level's marker is stripped
+                while self.current() == Token::RightAngle {
+                    inner.push(Token::BlockQuote);
+                    self.bump();
+                }
+            }
+            let end = self.line_end(self.position);
+            while self.position < end {
+                inner.push(self.current());
+                self.bump();
+            }
+            // a blank line or any new block construct ends the quote
+            if self.current() != Token::SoftBreak {
+                break;
+            }
+            let next = self.position + 1;
+            let continues = matches!(self.input.get(next), Some(Token::BlockQuote))
+                || (self.quote_lazy_line(next) && inner.last() != Some(&Token::BlockQuote));
+            if !continues {
+                break;
+            }
+            inner.push(Token::SoftBreak);
+            self.bump();
+        }
+
+        // a `[!KIND]` marker alone on the first quoted line turns the
+        // quote into a callout, the marker line is dropped
+        let callout = Self::callout_label(&inner);
+        if let Some((_, consumed)) = &callout {
+            inner.drain(..*consumed);
+        }
+
+        let mut parser = Parser::new(inner);
+        parser.set_tab_width(self.tab_width);
+        parser.max_depth = self.max_depth;
+        parser.depth = self.depth + 1;
+        let nodes = parser.parse()?;
+        // the nested parser's warnings belong to this document
+        self.warnings.append(&mut parser.warnings);
+        if let Some((label, _)) = callout {
+            let mut chars = label.chars();
+            let title = match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect(),
+                None => String::new(),
+            };
+            return Ok(Node::Callout {
+                kind: CalloutKind::from_label(&label),
+                title,
+                children: nodes,
+            });
+        }
+        Ok(Node::BlockQuote(nodes))
+    }
+
+    /// the `[!label]` at the head of a quote body, `None` when the
+    /// first line is anything else, the returned count covers the
+    /// marker tokens plus the line break after them
+    fn callout_label(inner: &[Token]) -> Option<(String, usize)> {
+        if inner.first() != Some(&Token::LeftSquare) || inner.get(1) != Some(&Token::Bang) {
+            return None;
+        }
+        let Some(Token::Indent(label)) = inner.get(2) else {
+            return None;
+        };
+        if inner.get(3) != Some(&Token::RightSquare) {
+            return None;
+        }
+        match inner.get(4) {
+            None => Some((label.to_string(), 4)),
+            Some(Token::SoftBreak) => Some((label.to_string(), 5)),
+            // trailing content keeps the marker literal
+            Some(_) => None,
+        }
+    }
+
+    /// whether the unmarked line at `pos` lazily continues a quote's
+    /// paragraph, block constructs always interrupt
+    fn quote_lazy_line(&self, pos: usize) -> bool {
+        if matches!(
+            self.input.get(pos),
+            None | Some(
+                Token::SoftBreak
+                    | Token::HardBreak
+                    | Token::Eof
+                    | Token::Heading(_)
+                    | Token::CodeBlock { .. }
+                    | Token::BlockQuote
+            )
+        ) {
+            return false;
+        }
+        self.list_marker(pos).is_none() && self.thematic_break(pos).is_none()
+    }
+
+    /// the per-column alignment when the line at `pos` is a table
+    /// delimiter row like `| --- | :-: | --: |`
+    fn table_align(&self, pos: usize) -> Option<Vec<Align>> {
+        if !matches!(self.input.get(pos)?, Token::Pipe) {
+            return None;
+        }
+        let mut i = pos + 1;
+        let mut align: Vec<Align> = Vec::new();
+        loop {
+            while matches!(self.input.get(i), Some(Token::WhiteSpace)) {
+                i += 1;
+            }
+            if matches!(
+                self.input.get(i),
+                None | Some(Token::SoftBreak | Token::HardBreak | Token::Eof)
+            ) {
+                break;
+            }
+            let left = matches!(self.input.get(i), Some(Token::Colon));
+            if left {
+                i += 1;
+            }
+            let mut dashes = 0;
+            loop {
+                match self.input.get(i) {
+                    Some(Token::Dash) => dashes += 1,
+                    Some(Token::Rule('-', n)) => dashes += n,
+                    _ => break,
+                }
+                i += 1;
+            }
+            if dashes == 0 {
+                return None;
+            }
+            let right = matches!(self.input.get(i), Some(Token::Colon));
+            if right {
+                i += 1;
+            }
+            while matches!(self.input.get(i), Some(Token::WhiteSpace)) {
+                i += 1;
+            }
+            if !matches!(self.input.get(i), Some(Token::Pipe)) {
+                return None;
+            }
+            i += 1;
+            align.push(match (left, right) {
+                (true, true) => Align::Center,
+                (false, true) => Align::Right,
+                _ => Align::Left,
+            });
+        }
+        if align.is_empty() {
+            None
+        } else {
+            Some(align)
+        }
+    }
+
+    /// parse a header row, its delimiter row and any body rows, rows
+    /// shorter than the delimiter row pad out with empty cells
+    fn parse_table(&mut self, align: Vec<Align>) -> Result<Node, Error> {
+        let mut header = self.parse_table_row()?;
+        header.resize(align.len(), Vec::new());
+        // skip the break and the delimiter row
+        self.bump();
+        self.position = self.line_end(self.position);
+
+        let mut rows: Vec<Vec<Vec<Inline>>> = Vec::new();
+        while matches!(self.current(), Token::SoftBreak)
+            && matches!(self.input.get(self.position + 1), Some(Token::Pipe))
+        {
+            self.bump();
+            let row_start = self.position;
+            let mut row = self.parse_table_row()?;
+            if row.len() != align.len() {
+                self.warnings.push(Warning {
+                    span: self.source_span(row_start, self.position),
+                    message: format!(
+                        "table row has {} cells, expected {}",
+                        row.len(),
+                        align.len()
+                    ),
+                });
+            }
+            row.resize(align.len(), Vec::new());
+            rows.push(row);
+        }
+        Ok(Node::Table {
+            align,
+            header,
+            rows,
+        })
+    }
+
+    /// split the current line at `|` into inline cells, padding spaces
+    /// around each cell are not part of its content
+    fn parse_table_row(&mut self) -> Result<Vec<Vec<Inline>>, Error> {
+        let end = self.line_end(self.position);
+        let mut cells: Vec<Vec<Inline>> = Vec::new();
+        // the leading pipe is row syntax, not a cell
+        self.bump();
+        while self.position < end {
+            let mut cell_end = self.position;
+            while cell_end < end && !matches!(self.input[cell_end], Token::Pipe) {
+                cell_end += 1;
+            }
+            while self.position < cell_end && self.current() == Token::WhiteSpace {
+                self.bump();
+            }
+            let mut trimmed = cell_end;
+            while trimmed > self.position && matches!(self.input[trimmed - 1], Token::WhiteSpace) {
+                trimmed -= 1;
+            }
+            let inline = self.parse_inline_run(trimmed)?;
+            cells.push(inline);
+            self.position = cell_end;
+            if self.current() == Token::Pipe {
+                self.bump();
+            }
+        }
+        self.position = end;
+        Ok(cells)
+    }
+
+    /// the index of the break token ending the line at `pos`
+    fn line_end(&self, pos: usize) -> usize {
+        let mut end = pos;
+        while end < self.input.len()
+            && !matches!(
+                self.input[end],
+                Token::SoftBreak | Token::HardBreak | Token::Eof
+            )
+        {
+            end += 1;
+        }
+        end
+    }
+
+    /// collect inline content up to (but not past) the next line break
+    fn parse_inline_until_break(&mut self) -> Result<Vec<Inline>, Error> {
+        let end = self.line_end(self.position);
+        let inline = self.parse_inline_run(end)?;
+        self.position = end;
+        Ok(inline)
+    }
+
+    /// collect inline content up to the token index `end`
+    fn parse_inline_run(&mut self, end: usize) -> Result<Vec<Inline>, Error> {
+        let mut inline: Vec<Inline> = Vec::new();
+        let mut text = String::new();
+        // length of the whitespace token run the line ends on, two or
+        // more is the hard-break marker `take_hard_break` looks for
+        let mut ws_run = 0usize;
+
+        while self.position < end {
+            if self.current() == Token::BackSlash {
+                if let Some(escaped) = self.try_escape(end) {
+                    text.push(escaped);
+                    continue;
+                }
+            }
+            if self.current() == Token::Bang {
+                if let Some(node) = self.try_image(end)? {
+                    Self::flush_text(&mut text, &mut inline);
+                    inline.push(node);
+                    continue;
+                }
+            }
+            if self.current() == Token::LeftSquare
+                && matches!(self.input.get(self.position + 1), Some(Token::Caret))
+            {
+                if let Some(node) = self.try_footnote_ref(end) {
+                    Self::flush_text(&mut text, &mut inline);
+                    inline.push(node);
+                    continue;
+                }
+            }
+            if self.current() == Token::LeftSquare {
+                if let Some(node) = self.try_link(end)? {
+                    Self::flush_text(&mut text, &mut inline);
+                    inline.push(node);
+                    continue;
+                }
+            }
+            if self.current() == Token::LeftAngle {
+                if let Some(node) = self.try_autolink(end) {
+                    Self::flush_text(&mut text, &mut inline);
+                    inline.push(node);
+                    continue;
+                }
+                if let Some(node) = self.try_inline_html(end) {
+                    Self::flush_text(&mut text, &mut inline);
+                    inline.push(node);
+                    continue;
+                }
+            }
+            if self.current() == Token::Ampersand {
+                if let Some(decoded) = self.try_entity(end) {
+                    text.push(decoded);
+                    continue;
+                }
+            }
+            if self.current() == Token::BackTick {
+                if let Some(node) = self.try_code_span(end) {
+                    Self::flush_text(&mut text, &mut inline);
+                    inline.push(node);
+                    continue;
+                }
+            }
+            if self.current() == Token::Dollar {
+                if let Some(node) = self.try_math_span(end) {
+                    Self::flush_text(&mut text, &mut inline);
+                    inline.push(node);
+                    continue;
+                }
+            }
+            #[cfg(feature = "emoji")]
+            if self.current() == Token::Colon {
+                if let Some(emoji) = self.try_emoji(end) {
+                    text.push_str(emoji);
+                    continue;
+                }
+            }
+            if self.current() == Token::At {
+                if let Some(node) = self.try_mention(end, &text) {
+                    Self::flush_text(&mut text, &mut inline);
+                    inline.push(node);
+                    continue;
+                }
+            }
+            if matches!(self.current(), Token::Caret | Token::Tilde) {
+                if let Some(node) = self.try_supsub(end)? {
+                    Self::flush_text(&mut text, &mut inline);
+                    inline.push(node);
+                    continue;
+                }
+            }
+            if let Some((ch, strength, width)) = self.delimiter_run(self.position) {
+                if let Some(node) = self.try_emphasis(ch, strength, width, end, &mut text)? {
+                    Self::flush_text(&mut text, &mut inline);
+                    inline.push(node);
+                    continue;
+                }
+                // no matching closer, the delimiter is literal text
+                text.push_str(&ch.to_string().repeat(strength));
+                for _ in 0..width {
+                    self.bump();
+                }
+                continue;
+            }
+            let tk = self.current();
+            if matches!(tk, Token::WhiteSpace | Token::Tab) {
+                // runs of spaces and tabs collapse to a single space, a
+                // run opening the line is dropped entirely
+                ws_run += 1;
+                let at_line_start = text.is_empty() && inline.is_empty();
+                if !text.ends_with(' ') && !at_line_start {
+                    text.push(' ');
+                }
+                self.bump();
+                continue;
+            }
+            ws_run = 0;
+            text.push_str(&Self::token_literal(&tk));
+            self.bump();
+        }
+
+        if ws_run >= 2 {
+            // keep the collapsed run recognizable as a hard break
+            text.push(' ');
+        } else {
+            // a single trailing space trims away
+            while text.ends_with(' ') {
+                text.pop();
+            }
+        }
+        Self::flush_text(&mut text, &mut inline);
+        Ok(inline)
+    }
+
+    /// parse a `[^id]` footnote reference at the current position,
+    /// labels are assigned sequentially by first reference, `None`
+    /// keeps the text literal and warns when `id` has no definition
+    fn try_footnote_ref(&mut self, end: usize) -> Option<Inline> {
+        if !self.config.footnotes {
+            return None;
+        }
+        let mut close = self.position + 2;
+        loop {
+            if close >= end {
+                return None;
+            }
+            if self.input[close] == Token::RightSquare {
+                break;
+            }
+            close += 1;
+        }
+        let id = self.literal_range(self.position + 2, close).to_lowercase();
+        if !self.footnote_defs.contains_key(&id) {
+            self.warnings.push(Warning {
+                span: self.source_span(self.position, close + 1),
+                message: format!("undefined footnote reference `{id}`"),
+            });
+            return None;
+        }
+        let number = match self.footnote_order.iter().position(|seen| *seen == id) {
+            Some(i) => i + 1,
+            None => {
+                self.footnote_order.push(id);
+                self.footnote_order.len()
+            }
+        };
+        self.position = close + 1;
+        Some(Inline::FootnoteRef(number.to_string()))
+    }
+
+    /// replace a `:name:` emoji shortcode at the current position via
+    /// the built-in table, `None` keeps unknown codes and lone colons
+    /// literal
+    #[cfg(feature = "emoji")]
+    fn try_emoji(&mut self, end: usize) -> Option<&'static str> {
+        let mut close = self.position + 1;
+        let mut name = String::new();
+        loop {
+            if close >= end {
+                return None;
+            }
+            match &self.input[close] {
+                Token::Colon => break,
+                Token::Indent(s) => name.push_str(s),
+                Token::Undersocre => name.push('_'),
+                Token::Plus => name.push('+'),
+                Token::Dash => name.push('-'),
+                _ => return None,
+            }
+            close += 1;
+        }
+        if close == self.position + 1 {
+            return None;
+        }
+        let emoji = super::emoji::lookup(&name)?;
+        self.position = close + 1;
+        Some(emoji)
+    }
+
+    /// the github-style anchor for a heading's `inline` content,
+    /// lowercased with spaces as hyphens and punctuation stripped, a
+    /// repeated base gains a `-1`, `-2`... suffix
+    fn slug_for(&mut self, inline: &[Inline]) -> String {
+        let mut text = String::new();
+        Self::flatten_text(inline, &mut text);
+        let base: String = text
+            .to_lowercase()
+            .chars()
+            .filter_map(|c| match c {
+                ' ' => Some('-'),
+                c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+                _ => None,
+            })
+            .collect();
+        let seen = self.slug_counts.entry(base.clone()).or_insert(0);
+        let slug = if *seen == 0 {
+            base.clone()
+        } else {
+            format!("{base}-{seen}")
+        };
+        *seen += 1;
+        slug
+    }
+
+    /// point `#slug` links at the heading whose slug matches, a miss
+    /// leaves the anchor unset and records a warning
+    fn resolve_anchors(&mut self, nodes: &mut [SpannedNode]) {
+        let mut slugs: BTreeMap<String, NodeId> = BTreeMap::new();
+        for (id, (node, _)) in nodes.iter().enumerate() {
+            if let Node::Heading { slug, .. } = node {
+                slugs.entry(slug.clone()).or_insert(id);
+            }
+        }
+        for (node, _) in nodes.iter_mut() {
+            self.resolve_node(node, &slugs);
+        }
+    }
+
+    fn resolve_node(&mut self, node: &mut Node, slugs: &BTreeMap<String, NodeId>) {
+        match node {
+            Node::Heading { inline, .. } | Node::Paragraph(inline) => {
+                self.resolve_inline(inline, slugs)
+            }
+            Node::List { items, .. } => {
+                for item in items {
+                    self.resolve_inline(&mut item.inline, slugs);
+                    for child in &mut item.children {
+                        self.resolve_node(child, slugs);
+                    }
+                }
+            }
+            Node::Table { header, rows, .. } => {
+                for cell in header.iter_mut().chain(rows.iter_mut().flatten()) {
+                    self.resolve_inline(cell, slugs);
+                }
+            }
+            Node::BlockQuote(inner) => {
+                for child in inner {
+                    self.resolve_node(child, slugs);
+                }
+            }
+            Node::Callout { children, .. } => {
+                for child in children {
+                    self.resolve_node(child, slugs);
+                }
+            }
+            Node::DefinitionList(entries) => {
+                for (term, defs) in entries {
+                    self.resolve_inline(term, slugs);
+                    for def in defs {
+                        self.resolve_inline(def, slugs);
+                    }
+                }
+            }
+            Node::FootnoteDefs(defs) => {
+                for (_, inline) in defs {
+                    self.resolve_inline(inline, slugs);
+                }
+            }
+            Node::Section {
+                heading, children, ..
+            } => {
+                self.resolve_inline(heading, slugs);
+                for child in children {
+                    self.resolve_node(child, slugs);
+                }
+            }
+            Node::CodeBlock { .. } | Node::Html(_) | Node::MathBlock(_) | Node::Rule => {}
+        }
+    }
+
+    fn resolve_inline(&mut self, inline: &mut [Inline], slugs: &BTreeMap<String, NodeId>) {
+        for node in inline {
+            match node {
+                Inline::Link {
+                    text, href, anchor, ..
+                } => {
+                    self.resolve_inline(text, slugs);
+                    if let Some(target) = href.strip_prefix('#') {
+                        *anchor = slugs.get(target).copied();
+                        if anchor.is_none() {
+                            self.warnings.push(Warning {
+                                span: None,
+                                message: format!("link to unknown anchor `#{target}`"),
+                            });
+                        }
+                    }
+                }
+                Inline::Bold(inner)
+                | Inline::Italic(inner)
+                | Inline::Superscript(inner)
+                | Inline::Subscript(inner) => self.resolve_inline(inner, slugs),
+                _ => {}
+            }
+        }
+    }
+
+    /// parse a `![alt](src)` image at the current position by reusing
+    /// the link machinery past the bang, `None` keeps the bang literal
+    fn try_image(&mut self, end: usize) -> Result<Option<Inline>, Error> {
+        if self.input.get(self.position + 1) != Some(&Token::LeftSquare) {
+            return Ok(None);
+        }
+        let start = self.position;
+        self.bump();
+        match self.try_link(end)? {
+            Some(Inline::Link { text, href, .. }) => {
+                let mut alt = String::new();
+                Self::flatten_text(&text, &mut alt);
+                Ok(Some(Inline::Image { alt, src: href }))
+            }
+            _ => {
+                self.position = start;
+                Ok(None)
+            }
+        }
+    }
+
+    /// the text content of `inline` with all markup dropped, the alt
+    /// text an image placeholder is labelled with
+    fn flatten_text(inline: &[Inline], out: &mut String) {
+        for node in inline {
+            match node {
+                Inline::Text(text) | Inline::Code(text) => out.push_str(text),
+                Inline::Bold(inner)
+                | Inline::Italic(inner)
+                | Inline::Superscript(inner)
+                | Inline::Subscript(inner) => Self::flatten_text(inner, out),
+                Inline::Link { text, .. } => Self::flatten_text(text, out),
+                _ => {}
+            }
+        }
+    }
+
+    /// parse `^text^` superscript or `~text~` subscript at the current
+    /// position, the run must close before any whitespace so `2^10 and
+    /// 3^4` stays literal, `None` keeps an unbalanced marker literal
+    fn try_supsub(&mut self, end: usize) -> Result<Option<Inline>, Error> {
+        let marker = self.current();
+        let mut close = self.position + 1;
+        loop {
+            if close >= end {
+                return Ok(None);
+            }
+            match &self.input[close] {
+                tk if *tk == marker => break,
+                Token::WhiteSpace | Token::Tab => return Ok(None),
+                _ => close += 1,
+            }
+        }
+        if close == self.position + 1 {
+            return Ok(None);
+        }
+        self.bump();
+        let inner = self.parse_inline_run(close)?;
+        self.position = close + 1;
+        Ok(Some(if marker == Token::Caret {
+            Inline::Superscript(inner)
+        } else {
+            Inline::Subscript(inner)
+        }))
+    }
+
+    /// parse an `@name` mention at the current position, only an `@`
+    /// starting a word qualifies so `a@b.com` and a trailing `@` stay
+    /// literal text
+    fn try_mention(&mut self, end: usize, preceding: &str) -> Option<Inline> {
+        if preceding
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric())
+        {
+            return None;
+        }
+        if self.position + 1 >= end {
+            return None;
+        }
+        if let Token::Indent(name) = &self.input[self.position + 1] {
+            let name = name.to_string();
+            self.position += 2;
+            return Some(Inline::Mention(name));
+        }
+        None
+    }
+
+    /// capture the contents of a `$$...$$` display math block, the
+    /// closing delimiter may sit on the same line or a later one, a
+    /// missing closer captures to the end of input
+    fn parse_math_block(&mut self) -> Result<Node, Error> {
+        self.bump();
+        self.bump();
+        if self.current() == Token::SoftBreak {
+            self.bump();
+        }
+        let mut body = String::new();
+        while !self.at_end() {
+            if self.current() == Token::Dollar
+                && self.input.get(self.position + 1) == Some(&Token::Dollar)
+            {
+                self.bump();
+                self.bump();
+                break;
+            }
+            match self.current() {
+                Token::SoftBreak | Token::HardBreak => body.push('\n'),
+                tk => body.push_str(&Self::token_literal(&tk)),
+            }
+            self.bump();
+        }
+        Ok(Node::MathBlock(body.trim_end_matches('\n').into()))
+    }
+
+    /// parse a `$...$` math span at the current position, the contents
+    /// are captured literally, `None` keeps an unbalanced `$` literal
+    fn try_math_span(&mut self, end: usize) -> Option<Inline> {
+        let mut close = self.position + 1;
+        loop {
+            if close >= end {
+                return None;
+            }
+            if self.input[close] == Token::Dollar {
+                break;
+            }
+            close += 1;
+        }
+        if close == self.position + 1 {
+            return None;
+        }
+        let body = self.literal_range(self.position + 1, close);
+        self.position = close + 1;
+        Some(Inline::Math(body))
+    }
+
+    /// the token index of the `>` closing an html tag opening at `at`,
+    /// `None` when the run does not look like a tag, a tag name
+    /// (optionally behind a closing `/`) must follow the `<` directly
+    /// and attributes must carry a `=` so `a < b` and `<not a link>`
+    /// stay literal text
+    fn html_tag_end(&self, at: usize, end: usize) -> Option<usize> {
+        let name_at = if self.input.get(at + 1) == Some(&Token::Slash) {
+            at + 2
+        } else {
+            at + 1
+        };
+        match self.input.get(name_at) {
+            Some(Token::Indent(name)) if name.starts_with(|c: char| c.is_ascii_alphabetic()) => {}
+            _ => return None,
+        }
+        let mut close = name_at + 1;
+        let mut saw_space = false;
+        let mut saw_eq = false;
+        loop {
+            if close >= end {
+                return None;
+            }
+            match self.input[close] {
+                Token::RightAngle => break,
+                Token::WhiteSpace | Token::Tab => saw_space = true,
+                Token::Equal => saw_eq = true,
+                _ => {}
+            }
+            close += 1;
+        }
+        if saw_space && !saw_eq {
+            return None;
+        }
+        Some(close)
+    }
+
+    /// like `html_tag_end` but for the block level, a scheme or email
+    /// after the name means the line is an autolink paragraph instead
+    fn html_block_start(&self, at: usize) -> bool {
+        if self.html_tag_end(at, self.line_end(at)).is_none() {
+            return false;
+        }
+        let name_at = if self.input.get(at + 1) == Some(&Token::Slash) {
+            at + 2
+        } else {
+            at + 1
+        };
+        !matches!(self.input.get(name_at + 1), Some(Token::Colon | Token::At))
+    }
+
+    /// capture a block of raw html, the block runs until a blank line or
+    /// the end of input and keeps its text uninterpreted
+    fn parse_html_block(&mut self) -> Result<Node, Error> {
+        let mut raw = String::new();
+        loop {
+            let end = self.line_end(self.position);
+            if !raw.is_empty() {
+                raw.push('\n');
+            }
+            raw.push_str(&self.literal_range(self.position, end));
+            self.position = end;
+            self.bump();
+            if self.at_end() || matches!(self.current(), Token::SoftBreak | Token::HardBreak) {
+                break;
+            }
+        }
+        Ok(Node::Html(raw))
+    }
+
+    /// capture a `<tag ...>`/`</tag>` raw inline html tag at the current
+    /// position, `None` keeps the angle bracket literal
+    fn try_inline_html(&mut self, end: usize) -> Option<Inline> {
+        let close = self.html_tag_end(self.position, end)?;
+        let raw = format!("<{}>", self.literal_range(self.position + 1, close));
+        self.position = close + 1;
+        Some(Inline::Html(raw))
+    }
+
+    /// parse a `<https://...>`/`<user@host>` autolink at the current
+    /// position, `None` keeps the angle bracket literal
+    fn try_autolink(&mut self, end: usize) -> Option<Inline> {
+        if !self.config.autolinks {
+            return None;
+        }
+        let mut close = self.position + 1;
+        loop {
+            if close >= end {
+                return None;
+            }
+            if self.input[close] == Token::RightAngle {
+                break;
+            }
+            close += 1;
+        }
+        let target = self.literal_range(self.position + 1, close);
+        if target.is_empty() || target.contains(' ') {
+            return None;
+        }
+        let href = if Self::has_scheme(&target) {
+            target.clone()
+        } else if Self::is_email(&target) {
+            format!("mailto:{target}")
+        } else {
+            return None;
+        };
+        self.position = close + 1;
+        Some(Inline::Link {
+            text: vec![Inline::Text(target)],
+            href,
+            title: None,
+            anchor: None,
+        })
+    }
+
+    fn has_scheme(target: &str) -> bool {
+        if target.starts_with("mailto:") {
+            return true;
+        }
+        match target.split_once("://") {
+            Some((scheme, rest)) => {
+                !scheme.is_empty()
+                    && !rest.is_empty()
+                    && scheme
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+            }
+            None => false,
+        }
+    }
+
+    fn is_email(target: &str) -> bool {
+        match target.split_once('@') {
+            Some((user, host)) => {
+                !user.is_empty()
+                    && host.contains('.')
+                    && !host.starts_with('.')
+                    && !host.ends_with('.')
+            }
+            None => false,
+        }
+    }
+
+    /// a backslash before ASCII punctuation escapes it, stripping its
+    /// syntactic role, `None` keeps the backslash literal (e.g. `\q`)
+    fn try_escape(&mut self, end: usize) -> Option<char> {
+        if self.position + 1 >= end {
+            return None;
+        }
+        if let Token::Rule(c, n) = self.input[self.position + 1] {
+            // only the first character of a run is escaped, the rest
+            // keeps its syntactic role
+            self.input[self.position + 1] = match (c, n - 1) {
+                ('-', 1) => Token::Dash,
+                ('=', 1) => Token::Equal,
+                ('*', 1) => Token::Asterisk,
+                (c, rest) => Token::Rule(c, rest),
+            };
+            self.bump();
+            return Some(c);
+        }
+        let literal = Self::token_literal(&self.input[self.position + 1]);
+        let first = literal.chars().next()?;
+        if literal.len() != 1 || !first.is_ascii_punctuation() {
+            return None;
+        }
+        self.bump();
+        self.bump();
+        Some(first)
+    }
+
+    /// decode an html entity (`&amp;`, `&#39;`, `&#x27;`) at the current
+    /// position, `None` leaves the ampersand and whatever follows literal
+    fn try_entity(&mut self, end: usize) -> Option<char> {
+        // named: `&name;`
+        if self.position + 2 < end {
+            if let (Some(Token::Indent(name)), Some(Token::SemiColon)) = (
+                self.input.get(self.position + 1),
+                self.input.get(self.position + 2),
+            ) {
+                let decoded = match name.as_ref() {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "nbsp" => Some('\u{a0}'),
+                    _ => None,
+                };
+                if let Some(decoded) = decoded {
+                    self.position += 3;
+                    return Some(decoded);
+                }
+            }
+        }
+        // numeric: `&#NN;` or `&#xNN;`
+        if self.position + 3 < end {
+            if let (Some(Token::Heading(1)), Some(Token::Indent(digits)), Some(Token::SemiColon)) = (
+                self.input.get(self.position + 1),
+                self.input.get(self.position + 2),
+                self.input.get(self.position + 3),
+            ) {
+                let value = if let Some(hex) = digits
+                    .strip_prefix('x')
+                    .or_else(|| digits.strip_prefix('X'))
+                {
+                    u32::from_str_radix(hex, 16).ok()?
+                } else {
+                    digits.parse().ok()?
+                };
+                let decoded = char::from_u32(value)?;
+                self.position += 4;
+                return Some(decoded);
+            }
+        }
+        None
+    }
+
+    /// parse a backtick code span at the current position, the contents
+    /// are kept literal and a run of N backticks is only closed by a run
+    /// of exactly N, `None` means the backticks should degrade to
+    /// literal text
+    fn try_code_span(&mut self, end: usize) -> Option<Inline> {
+        let open = self.backtick_run(self.position);
+        let mut pos = self.position + open;
+        while pos < end {
+            let run = self.backtick_run(pos);
+            if run == 0 {
+                pos += 1;
+                continue;
+            }
+            if run == open {
+                let mut code = String::new();
+                for tk in &self.input[self.position + open..pos] {
+                    code.push_str(&Self::token_literal(tk));
+                }
+                self.position = pos + run;
+                return Some(Inline::Code(code));
+            }
+            pos += run;
+        }
+        None
+    }
+
+    /// the length of the run of `BackTick` tokens starting at `pos`
+    fn backtick_run(&self, pos: usize) -> usize {
+        let mut len = 0;
+        while matches!(self.input.get(pos + len), Some(Token::BackTick)) {
+            len += 1;
+        }
+        len
+    }
+
+    /// the emphasis delimiter starting at `pos` as (char, strength in
+    /// characters, width in tokens), `**` arrives pre-coalesced as a
+    /// `Rule('*', 2)` while `__` is two separate `Undersocre` tokens
+    fn delimiter_run(&self, pos: usize) -> Option<(char, usize, usize)> {
+        match self.input.get(pos)? {
+            Token::Asterisk => Some(('*', 1, 1)),
+            Token::Rule('*', n) => Some(('*', *n, 1)),
+            Token::Undersocre => {
+                let mut len = 1;
+                while matches!(self.input.get(pos + len), Some(Token::Undersocre)) {
+                    len += 1;
+                }
+                Some(('_', len, len))
+            }
+            _ => None,
+        }
+    }
+
+    /// parse `*x*`/`_x_` style emphasis at the current position, `None`
+    /// means the delimiter should be treated as literal text
+    fn try_emphasis(
+        &mut self,
+        ch: char,
+        strength: usize,
+        width: usize,
+        end: usize,
+        text: &mut String,
+    ) -> Result<Option<Inline>, Error> {
+        if !self.can_open(ch, self.position, width) {
+            return Ok(None);
+        }
+        // prefer a closer of the same strength so `**a *b* c**` nests,
+        // otherwise the shorter run wins and the leftover stays literal
+        let from = self.position + width;
+        let Some((close, c_strength, c_width)) = self
+            .find_closer(ch, from, end, Some(strength))
+            .or_else(|| self.find_closer(ch, from, end, None))
+        else {
+            return Ok(None);
+        };
+        let used = strength.min(c_strength);
+        // unmatched opener chars degrade to literal text before the node
+        for _ in used..strength {
+            text.push(ch);
+        }
+        self.position += width;
+        let inner = self.parse_inline_run(close)?;
+        self.position = close;
+        self.consume_closer(ch, c_strength, c_width, used);
+
+        let node = match used {
+            1 => Inline::Italic(inner),
+            2 => Inline::Bold(inner),
+            _ => Inline::Bold(vec![Inline::Italic(inner)]),
+        };
+        Ok(Some(node))
+    }
+
+    /// scan for the next closing run for `ch` in `[from, end)`, code
+    /// spans are opaque to the scan so a delimiter inside backticks
+    /// never closes emphasis, `strength` restricts the match to runs of
+    /// that exact length
+    fn find_closer(
+        &self,
+        ch: char,
+        from: usize,
+        end: usize,
+        strength: Option<usize>,
+    ) -> Option<(usize, usize, usize)> {
+        let mut pos = from;
+        while pos < end {
+            if matches!(self.input.get(pos), Some(Token::BackTick)) {
+                if let Some(after) = self.skip_code_span(pos, end) {
+                    pos = after;
+                    continue;
+                }
+            }
+            if let Some((c_ch, c_strength, c_width)) = self.delimiter_run(pos) {
+                if c_ch == ch
+                    && strength.map(|s| s == c_strength).unwrap_or(true)
+                    && self.can_close(ch, pos, c_width)
+                {
+                    return Some((pos, c_strength, c_width));
+                }
+                pos += c_width;
+                continue;
+            }
+            pos += 1;
+        }
+        None
+    }
+
+    /// the index just past the code span opening at `pos`, `None` when
+    /// the backtick run is never closed
+    fn skip_code_span(&self, pos: usize, end: usize) -> Option<usize> {
+        let open = self.backtick_run(pos);
+        let mut i = pos + open;
+        while i < end {
+            let run = self.backtick_run(i);
+            if run == open {
+                return Some(i + run);
+            }
+            i += run.max(1);
+        }
+        None
+    }
+
+    /// consume `used` delimiters of the closing run at the current
+    /// position, any leftover stays in the stream as a shorter run
+    fn consume_closer(&mut self, ch: char, strength: usize, width: usize, used: usize) {
+        if used >= strength {
+            for _ in 0..width {
+                self.bump();
+            }
+        } else if width == 1 {
+            // a single `Rule` token holds the whole run, shrink it
+            let rest = strength - used;
+            self.input[self.position] = match (ch, rest) {
+                ('*', 1) => Token::Asterisk,
+                ('_', 1) => Token::Undersocre,
+                (c, n) => Token::Rule(c, n),
+            };
+        } else {
+            // `_` runs are one token per delimiter char
+            for _ in 0..used {
+                self.bump();
+            }
+        }
+    }
+
+    /// whitespace for the purposes of the flanking rules, a line edge
+    /// counts as whitespace
+    fn is_flank_ws(tk: Option<&Token<'_>>) -> bool {
+        matches!(
+            tk,
+            None | Some(
+                Token::WhiteSpace
+                    | Token::Tab
+                    | Token::SoftBreak
+                    | Token::HardBreak
+                    | Token::Eof
+            )
+        )
+    }
+
+    /// punctuation for the purposes of the flanking rules
+    fn is_flank_punct(tk: Option<&Token<'_>>) -> bool {
+        matches!(
+            tk,
+            Some(
+                Token::LeftSquare
+                    | Token::RightSquare
+                    | Token::LeftParen
+                    | Token::RightParen
+                    | Token::LeftAngle
+                    | Token::RightAngle
+                    | Token::BlockQuote
+                    | Token::Dot
+                    | Token::Dash
+                    | Token::Equal
+                    | Token::Plus
+                    | Token::Asterisk
+                    | Token::Undersocre
+                    | Token::BackTick
+                    | Token::BackSlash
+                    | Token::Slash
+                    | Token::Colon
+                    | Token::SemiColon
+                    | Token::Pipe
+                    | Token::Tilde
+                    | Token::Bang
+                    | Token::Ampersand
+                    | Token::At
+                    | Token::Rule(_, _)
+            )
+        )
+    }
+
+    /// CommonMark left-flanking test for the run `[pos, pos + width)`
+    fn left_flanking(&self, pos: usize, width: usize) -> bool {
+        let next = self.input.get(pos + width);
+        let prev = pos.checked_sub(1).and_then(|p| self.input.get(p));
+        if Self::is_flank_ws(next) {
+            return false;
+        }
+        !Self::is_flank_punct(next) || Self::is_flank_ws(prev) || Self::is_flank_punct(prev)
+    }
+
+    /// CommonMark right-flanking test for the run `[pos, pos + width)`
+    fn right_flanking(&self, pos: usize, width: usize) -> bool {
+        let next = self.input.get(pos + width);
+        let prev = pos.checked_sub(1).and_then(|p| self.input.get(p));
+        if Self::is_flank_ws(prev) {
+            return false;
+        }
+        !Self::is_flank_punct(prev) || Self::is_flank_ws(next) || Self::is_flank_punct(next)
+    }
+
+    /// a run can open emphasis when it is left-flanking, `_` must
+    /// additionally not sit inside a word so foo_bar_baz stays literal
+    fn can_open(&self, ch: char, pos: usize, width: usize) -> bool {
+        let lf = self.left_flanking(pos, width);
+        if ch == '*' {
+            return lf;
+        }
+        let prev = pos.checked_sub(1).and_then(|p| self.input.get(p));
+        lf && (!self.right_flanking(pos, width) || Self::is_flank_punct(prev))
+    }
+
+    /// a run can close emphasis when it is right-flanking, with the same
+    /// intraword restriction for `_`
+    fn can_close(&self, ch: char, pos: usize, width: usize) -> bool {
+        let rf = self.right_flanking(pos, width);
+        if ch == '*' {
+            return rf;
+        }
+        let next = self.input.get(pos + width);
+        rf && (!self.left_flanking(pos, width) || Self::is_flank_punct(next))
+    }
+
+    /// parse an inline `[text](href "title")` or reference-style
+    /// `[text][id]`/`[text][]`/`[text]` link at the current position,
+    /// `None` means the `[` should degrade to literal text
+    fn try_link(&mut self, end: usize) -> Result<Option<Inline>, Error> {
+        let mut close_square = self.position + 1;
+        loop {
+            if close_square >= end {
+                return Ok(None);
+            }
+            if self.input[close_square] == Token::RightSquare {
+                break;
+            }
+            close_square += 1;
+        }
+        match self.input.get(close_square + 1) {
+            Some(Token::LeftParen) => self.finish_inline_link(close_square, end),
+            Some(Token::LeftSquare) => {
+                // full (`[text][id]`) or collapsed (`[text][]`) reference
+                let mut close_id = close_square + 2;
+                loop {
+                    if close_id >= end {
+                        return Ok(None);
+                    }
+                    if self.input[close_id] == Token::RightSquare {
+                        break;
+                    }
+                    close_id += 1;
+                }
+                let id = self.literal_range(close_square + 2, close_id);
+                let id = if id.is_empty() {
+                    self.literal_range(self.position + 1, close_square)
+                } else {
+                    id
+                };
+                let Some(href) = self.definitions.get(&id.to_lowercase()).cloned() else {
+                    self.warnings.push(Warning {
+                        span: self.source_span(self.position, close_id + 1),
+                        message: format!("dangling reference link `{id}`"),
+                    });
+                    return Ok(None);
+                };
+                self.bump();
+                let text = self.parse_inline_run(close_square)?;
+                self.position = close_id + 1;
+                Ok(Some(Inline::Link {
+                    text,
+                    href,
+                    title: None,
+                    anchor: None,
+                }))
+            }
+            _ => {
+                // shortcut reference, `[text]` on its own
+                let id = self.literal_range(self.position + 1, close_square);
+                let Some(href) = self.definitions.get(&id.to_lowercase()).cloned() else {
+                    return Ok(None);
+                };
+                self.bump();
+                let text = self.parse_inline_run(close_square)?;
+                self.position = close_square + 1;
+                Ok(Some(Inline::Link {
+                    text,
+                    href,
+                    title: None,
+                    anchor: None,
+                }))
+            }
+        }
+    }
+
+    /// finish an inline link once `[text](` has been recognized
+    fn finish_inline_link(
+        &mut self,
+        close_square: usize,
+        end: usize,
+    ) -> Result<Option<Inline>, Error> {
+        let mut close_paren = close_square + 2;
+        loop {
+            if close_paren >= end {
+                return Ok(None);
+            }
+            if self.input[close_paren] == Token::RightParen {
+                break;
+            }
+            close_paren += 1;
+        }
+
+        // the bracketed text may itself hold emphasis
+        self.bump();
+        let text = self.parse_inline_run(close_square)?;
+        self.position = close_square + 2;
+
+        let mut target = String::new();
+        while self.position < close_paren {
+            let tk = self.current();
+            target.push_str(&Self::token_literal(&tk));
+            self.bump();
+        }
+        self.position = close_paren + 1;
+
+        let (href, title) = match target.split_once(' ') {
+            Some((href, title)) => {
+                let title = title.trim().trim_matches('"');
+                let title = if title.is_empty() {
+                    None
+                } else {
+                    Some(title.to_string())
+                };
+                (href.to_string(), title)
+            }
+            None => (target, None),
+        };
+
+        Ok(Some(Inline::Link {
+            text,
+            href,
+            title,
+            anchor: None,
+        }))
+    }
+
+    /// first pass over the stream, `[id]: url` lines are pulled out of
+    /// the input so reference links can resolve against them
+    fn collect_definitions(&mut self) -> Result<(), Error> {
+        let mut i = 0;
+        while i < self.input.len() {
+            let line_start =
+                i == 0 || matches!(self.input[i - 1], Token::SoftBreak | Token::HardBreak);
+            if line_start && self.input[i] == Token::LeftSquare {
+                // `[^id]: ...` footnote definitions first, `definition_at`
+                // would otherwise claim them as a `^id` link definition
+                if let Some((id, def_start, line_end)) = self.footnote_def_at(i) {
+                    let saved = self.position;
+                    self.position = def_start;
+                    let inline = self.parse_inline_run(line_end)?;
+                    self.position = saved;
+                    self.footnote_defs.insert(id, inline);
+                    self.input.drain(i..line_end);
+                    if !self.spans.is_empty() {
+                        self.spans.drain(i..line_end);
+                    }
+                    continue;
+                }
+                if let Some((id, url, line_end)) = self.definition_at(i) {
+                    self.definitions.insert(id, url);
+                    self.input.drain(i..line_end);
+                    // keep the span table aligned with the token stream
+                    if !self.spans.is_empty() {
+                        self.spans.drain(i..line_end);
+                    }
+                    continue;
+                }
+            }
+            i += 1;
+        }
+        Ok(())
+    }
+
+    /// the id, definition start and line end when the line at `pos` is a
+    /// `[^id]: ...` footnote definition
+    fn footnote_def_at(&self, pos: usize) -> Option<(String, usize, usize)> {
+        if !self.config.footnotes {
+            return None;
+        }
+        if self.input.get(pos + 1)? != &Token::Caret {
+            return None;
+        }
+        let mut i = pos + 2;
+        loop {
+            match self.input.get(i)? {
+                Token::RightSquare => break,
+                Token::SoftBreak | Token::HardBreak | Token::Eof => return None,
+                _ => i += 1,
+            }
+        }
+        if !matches!(self.input.get(i + 1), Some(Token::Colon)) {
+            return None;
+        }
+        let id = self.literal_range(pos + 2, i).to_lowercase();
+        if id.is_empty() {
+            return None;
+        }
+        let mut start = i + 2;
+        if matches!(self.input.get(start), Some(Token::WhiteSpace)) {
+            start += 1;
+        }
+        Some((id, start, self.line_end(start)))
+    }
+
+    /// the id, url and line end when the line at `pos` is a `[id]: url`
+    /// link definition
+    fn definition_at(&self, pos: usize) -> Option<(String, String, usize)> {
+        let mut i = pos + 1;
+        loop {
+            match self.input.get(i)? {
+                Token::RightSquare => break,
+                Token::SoftBreak | Token::HardBreak | Token::Eof => return None,
+                _ => i += 1,
+            }
+        }
+        if !matches!(self.input.get(i + 1), Some(Token::Colon)) {
+            return None;
+        }
+        let id = self.literal_range(pos + 1, i).to_lowercase();
+        let mut j = i + 2;
+        let mut url = String::new();
+        while j < self.input.len()
+            && !matches!(
+                self.input[j],
+                Token::SoftBreak | Token::HardBreak | Token::Eof
+            )
+        {
+            url.push_str(&Self::token_literal(&self.input[j]));
+            j += 1;
+        }
+        let url = url.trim().to_string();
+        if id.is_empty() || url.is_empty() {
+            return None;
+        }
+        Some((id, url, j))
+    }
+
+    /// the literal text of the tokens in `start..end`
+    fn literal_range(&self, start: usize, end: usize) -> String {
+        self.input[start..end.min(self.input.len())]
+            .iter()
+            .map(Self::token_literal)
+            .collect()
+    }
+
+    /// the heading level when the next line is a setext underline, the
+    /// underline must sit directly below the text with no blank line
+    fn setext_level(&self) -> Option<usize> {
+        if self.current() != Token::SoftBreak {
+            return None;
+        }
+        let level = match self.input.get(self.position + 1)? {
+            Token::Equal | Token::Rule('=', _) => 1,
+            Token::Dash | Token::Rule('-', _) => 2,
+            _ => return None,
+        };
+        if matches!(
+            self.input.get(self.position + 2),
+            None | Some(Token::SoftBreak) | Some(Token::HardBreak) | Some(Token::Eof)
+        ) {
+            Some(level)
+        } else {
+            None
+        }
+    }
+
+    /// the token count of the line at `pos` when it is a thematic break:
+    /// three or more `-`/`*`/`_` characters, optionally space separated
+    fn thematic_break(&self, pos: usize) -> Option<usize> {
+        let mut i = pos;
+        let mut marker: Option<char> = None;
+        let mut count = 0;
+        loop {
+            let (ch, n) = match self.input.get(i) {
+                None | Some(Token::SoftBreak | Token::HardBreak | Token::Eof) => break,
+                Some(Token::WhiteSpace) => {
+                    i += 1;
+                    continue;
+                }
+                Some(Token::Dash) => ('-', 1),
+                Some(Token::Asterisk) => ('*', 1),
+                Some(Token::Undersocre) => ('_', 1),
+                Some(Token::Rule(c @ ('-' | '*'), n)) => (*c, *n),
+                _ => return None,
+            };
+            if *marker.get_or_insert(ch) != ch {
+                return None;
+            }
+            count += n;
+            i += 1;
+        }
+        if count >= 3 {
+            Some(i - pos)
+        } else {
+            None
+        }
+    }
+
+    fn line_is_only_rule(&self) -> bool {
+        matches!(
+            self.input.get(self.position + 1),
+            None | Some(Token::SoftBreak) | Some(Token::HardBreak) | Some(Token::Eof)
+        )
+    }
+
+    fn flush_text(text: &mut String, inline: &mut Vec<Inline>) {
+        if !text.is_empty() {
+            inline.push(Inline::Text(core::mem::take(text)));
+        }
+    }
+
+    /// the literal text a token stands for when it has no special meaning
+    fn token_literal(tk: &Token<'_>) -> String {
+        match tk {
+            Token::Indent(s) => s.clone().into_owned(),
+            Token::WhiteSpace => " ".into(),
+            Token::Tab => "\t".into(),
+            Token::Heading(n) => "#".repeat(*n),
+            Token::LeftSquare => "[".into(),
+            Token::RightSquare => "]".into(),
+            Token::LeftParen => "(".into(),
+            Token::RightParen => ")".into(),
+            Token::LeftAngle => "<".into(),
+            Token::RightAngle | Token::BlockQuote => ">".into(),
+            Token::Dot => ".".into(),
+            Token::Dash => "-".into(),
+            Token::Equal => "=".into(),
+            Token::Plus => "+".into(),
+            Token::Asterisk => "*".into(),
+            Token::Undersocre => "_".into(),
+            Token::BackTick => "`".into(),
+            Token::BackSlash => "\\".into(),
+            Token::Slash => "/".into(),
+            Token::Colon => ":".into(),
+            Token::SemiColon => ";".into(),
+            Token::Pipe => "|".into(),
+            Token::Tilde => "~".into(),
+            Token::Bang => "!".into(),
+            Token::Ampersand => "&".into(),
+            Token::At => "@".into(),
+            Token::Caret => "^".into(),
+            Token::Dollar => "$".into(),
+            Token::Question => "?".into(),
+            Token::Punct(b) => (*b as char).to_string(),
+            Token::Rule(c, n) => c.to_string().repeat(*n),
+            Token::OrderedMarker(n) => format!("{}.", n),
+            Token::Illegal(b) => (*b as char).to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn current(&self) -> Token<'a> {
+        if self.position >= self.input.len() {
+            return Token::Eof;
+        }
+        self.input[self.position].clone()
+    }
+
+    fn bump(&mut self) {
+        self.position += 1;
+    }
+
+    fn at_end(&self) -> bool {
+        self.position >= self.input.len() || self.current() == Token::Eof
+    }
+
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use anyhow::{Ok, Result};
+
+    use crate::parser::lexer::Lexer;
+
+    use super::{
+        Align, CalloutKind, Frontmatter, FrontmatterFormat, Inline, ListItem, Node, ParseConfig,
+        Parser, Warning,
+    };
+
+    fn item(text: &str) -> ListItem {
+        ListItem {
+            inline: vec![Inline::Text(text.into())],
+            children: Vec::new(),
+            checked: None,
+        }
+    }
+
+    fn parse(md: &str) -> Result<Vec<Node>> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse(md)?;
+        let mut parser = Parser::new(tokens);
+        Ok(parser.parse()?)
+    }
+
+    #[test]
+    fn emphasis() -> Result<()> {
+        assert_eq!(
+            parse("*x*")?,
+            vec![Node::Paragraph(vec![Inline::Italic(vec![Inline::Text(
+                "x".into()
+            )])])]
+        );
+        assert_eq!(
+            parse("__x__")?,
+            vec![Node::Paragraph(vec![Inline::Bold(vec![Inline::Text(
+                "x".into()
+            )])])]
+        );
+        assert_eq!(
+            parse("***x***")?,
+            vec![Node::Paragraph(vec![Inline::Bold(vec![Inline::Italic(
+                vec![Inline::Text("x".into())]
+            )])])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn nested_emphasis() -> Result<()> {
+        assert_eq!(
+            parse("**a *b* c**")?,
+            vec![Node::Paragraph(vec![Inline::Bold(vec![
+                Inline::Text("a ".into()),
+                Inline::Italic(vec![Inline::Text("b".into())]),
+                Inline::Text(" c".into()),
+            ])])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unmatched_emphasis() -> Result<()> {
+        assert_eq!(
+            parse("a * b")?,
+            vec![Node::Paragraph(vec![Inline::Text("a * b".into())])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn mid_word_underscore() -> Result<()> {
+        assert_eq!(
+            parse("foo_bar_baz")?,
+            vec![Node::Paragraph(vec![Inline::Text("foo_bar_baz".into())])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn flat_list() -> Result<()> {
+        assert_eq!(
+            parse("- a\n* b\n- c")?,
+            vec![Node::List {
+                ordered: false,
+                tight: true,
+                items: vec![item("a"), item("b"), item("c")],
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn nested_list() -> Result<()> {
+        assert_eq!(
+            parse("- a\n  - b\n    - c\n- d")?,
+            vec![Node::List {
+                ordered: false,
+                tight: true,
+                items: vec![
+                    ListItem {
+                        inline: vec![Inline::Text("a".into())],
+                        children: vec![Node::List {
+                            ordered: false,
+                            tight: true,
+                            items: vec![ListItem {
+                                inline: vec![Inline::Text("b".into())],
+                                children: vec![Node::List {
+                                    ordered: false,
+                                    tight: true,
+                                    items: vec![item("c")],
+                                }],
+                                checked: None,
+                            }],
+                        }],
+                        checked: None,
+                    },
+                    item("d"),
+                ],
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn task_list_items() -> Result<()> {
+        assert_eq!(
+            parse("- [ ] a\n- [x] b")?,
+            vec![Node::List {
+                ordered: false,
+                tight: true,
+                items: vec![
+                    ListItem {
+                        checked: Some(false),
+                        ..item("a")
+                    },
+                    ListItem {
+                        checked: Some(true),
+                        ..item("b")
+                    },
+                ],
+            }]
+        );
+
+        // anything but a space or `x` between the brackets is plain text
+        assert_eq!(
+            parse("- [y] a")?,
+            vec![Node::List {
+                ordered: false,
+                tight: true,
+                items: vec![item("[y] a")],
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn wrapped_list_item() -> Result<()> {
+        assert_eq!(
+            parse("- a\n  wraps\n- b")?,
+            vec![Node::List {
+                ordered: false,
+                tight: true,
+                items: vec![
+                    item("a wraps"),
+                    item("b"),
+                ],
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn setext_headings() -> Result<()> {
+        assert_eq!(
+            parse("Title\n===")?,
+            vec![Node::Heading {
+                level: 1,
+                inline: vec![Inline::Text("Title".into())],
+                slug: "title".into(),
+            }]
+        );
+        assert_eq!(
+            parse("Title\n---")?,
+            vec![Node::Heading {
+                level: 2,
+                inline: vec![Inline::Text("Title".into())],
+                slug: "title".into(),
+            }]
+        );
+        // a blank line before the run keeps it a horizontal rule
+        assert_eq!(
+            parse("para\n\n---")?,
+            vec![
+                Node::Paragraph(vec![Inline::Text("para".into())]),
+                Node::Rule,
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn plain_link() -> Result<()> {
+        assert_eq!(
+            parse("[t](a.com)")?,
+            vec![Node::Paragraph(vec![Inline::Link {
+                text: vec![Inline::Text("t".into())],
+                href: "a.com".into(),
+                title: None,
+                anchor: None,
+            }])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn emphasized_link_text() -> Result<()> {
+        assert_eq!(
+            parse("[*t*](x)")?,
+            vec![Node::Paragraph(vec![Inline::Link {
+                text: vec![Inline::Italic(vec![Inline::Text("t".into())])],
+                href: "x".into(),
+                title: None,
+                anchor: None,
+            }])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn link_title() -> Result<()> {
+        assert_eq!(
+            parse("[t](a.com \"hi there\")")?,
+            vec![Node::Paragraph(vec![Inline::Link {
+                text: vec![Inline::Text("t".into())],
+                href: "a.com".into(),
+                title: Some("hi there".into()),
+                anchor: None,
+            }])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn bare_brackets() -> Result<()> {
+        assert_eq!(
+            parse("[not a link]")?,
+            vec![Node::Paragraph(vec![Inline::Text("[not a link]".into())])]
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip() -> Result<()> {
+        let nodes = parse("# Hi\n\n- a\n- *b*")?;
+
+        let json = super::to_json(&nodes);
+        let back: Vec<Node> = serde_json::from_str(&json)?;
+
+        assert_eq!(nodes, back);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tab_indented_list() -> Result<()> {
+        // a tab indents to the next tab stop, so it nests exactly like
+        // four spaces, even after a leading space
+        assert_eq!(parse("- a\n\t- b\n- c")?, parse("- a\n    - b\n- c")?);
+        assert_eq!(parse("- a\n \t- b")?, parse("- a\n    - b")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn custom_tab_width() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse("- a\n\t- b")?;
+        let mut parser = Parser::new(tokens);
+        parser.set_tab_width(8);
+
+        assert_eq!(parser.parse()?, parse("- a\n        - b")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn autolinks() -> Result<()> {
+        assert_eq!(
+            parse("<https://example.com>")?,
+            vec![Node::Paragraph(vec![Inline::Link {
+                text: vec![Inline::Text("https://example.com".into())],
+                href: "https://example.com".into(),
+                title: None,
+                anchor: None,
+            }])]
+        );
+        assert_eq!(
+            parse("<user@example.com>")?,
+            vec![Node::Paragraph(vec![Inline::Link {
+                text: vec![Inline::Text("user@example.com".into())],
+                href: "mailto:user@example.com".into(),
+                title: None,
+                anchor: None,
+            }])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn raw_html() -> Result<()> {
+        assert_eq!(
+            parse("<div class=\"x\">\ncontent\n</div>")?,
+            vec![Node::Html("<div class=\"x\">\ncontent\n</div>".into())]
+        );
+        assert_eq!(
+            parse("some <b>bold</b> text")?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("some ".into()),
+                Inline::Html("<b>".into()),
+                Inline::Text("bold".into()),
+                Inline::Html("</b>".into()),
+                Inline::Text(" text".into()),
+            ])]
+        );
+        // a comparison is not a tag
+        assert_eq!(
+            parse("a < b")?,
+            vec![Node::Paragraph(vec![Inline::Text("a < b".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn heading_slugs() -> Result<()> {
+        let nodes = parse("# My Heading!\n\n# Other\n\n# My Heading!")?;
+        let slugs: Vec<&str> = nodes
+            .iter()
+            .map(|node| match node {
+                Node::Heading { slug, .. } => slug.as_str(),
+                _ => "",
+            })
+            .collect();
+
+        assert_eq!(slugs, vec!["my-heading", "other", "my-heading-1"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn heading_level_accessor() -> Result<()> {
+        let nodes = parse("## Two\n\npara")?;
+        assert_eq!(nodes[0].heading_level(), Some(2));
+        assert_eq!(nodes[1].heading_level(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn toc_nesting() -> Result<()> {
+        let nodes = parse("# One\n\n## Two\n\n### Three\n\n## Four")?;
+        let toc = super::table_of_contents(&nodes, 1, 3);
+
+        let link = |text: &str, slug: &str| Inline::Link {
+            text: vec![Inline::Text(text.into())],
+            href: format!("#{slug}"),
+            title: None,
+            anchor: None,
+        };
+        let item = |inline, children| ListItem {
+            inline,
+            children,
+            checked: None,
+        };
+        assert_eq!(
+            toc,
+            Node::List {
+                ordered: false,
+                tight: true,
+                items: vec![item(
+                    vec![link("One", "one")],
+                    vec![Node::List {
+                        ordered: false,
+                        tight: true,
+                        items: vec![
+                            item(
+                                vec![link("Two", "two")],
+                                vec![Node::List {
+                                    ordered: false,
+                                    tight: true,
+                                    items: vec![item(vec![link("Three", "three")], vec![])],
+                                }],
+                            ),
+                            item(vec![link("Four", "four")], vec![]),
+                        ],
+                    }],
+                )],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn anchor_link_resolves() -> Result<()> {
+        let nodes = parse("# My Heading\n\n[back](#my-heading)")?;
+
+        assert_eq!(
+            nodes[1],
+            Node::Paragraph(vec![Inline::Link {
+                text: vec![Inline::Text("back".into())],
+                href: "#my-heading".into(),
+                title: None,
+                anchor: Some(0),
+            }])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_anchor_warns() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse("[gone](#nowhere)")?;
+        let mut parser = Parser::new(tokens);
+
+        let (nodes, warnings) = parser.parse_with_warnings()?;
+        assert_eq!(
+            nodes,
+            vec![Node::Paragraph(vec![Inline::Link {
+                text: vec![Inline::Text("gone".into())],
+                href: "#nowhere".into(),
+                title: None,
+                anchor: None,
+            }])]
+        );
+        assert_eq!(
+            warnings,
+            vec![Warning {
+                span: None,
+                message: "link to unknown anchor `#nowhere`".into(),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn whitespace_collapses() -> Result<()> {
+        assert_eq!(
+            parse("a    b")?,
+            vec![Node::Paragraph(vec![Inline::Text("a b".into())])]
+        );
+        // fenced code keeps its spacing
+        assert_eq!(
+            parse("```\na    b\n```")?,
+            vec![Node::CodeBlock {
+                lang: None,
+                body: "a    b\n".into(),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn image_inline() -> Result<()> {
+        assert_eq!(
+            parse("see ![logo](img.png) here")?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("see ".into()),
+                Inline::Image {
+                    alt: "logo".into(),
+                    src: "img.png".into(),
+                },
+                Inline::Text(" here".into()),
+            ])]
+        );
+        // a bang without a link stays literal
+        assert_eq!(
+            parse("hey!")?,
+            vec![Node::Paragraph(vec![Inline::Text("hey!".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn disabled_tables_stay_literal() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse("| a | b |\n| - | - |\n| 1 | 2 |")?;
+        let mut parser = Parser::new(tokens);
+        parser.set_config(ParseConfig {
+            tables: false,
+            ..ParseConfig::default()
+        });
+
+        assert_eq!(
+            parser.parse()?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("| a | b |".into()),
+                Inline::SoftBreak,
+                Inline::Text("| - | - |".into()),
+                Inline::SoftBreak,
+                Inline::Text("| 1 | 2 |".into()),
+            ])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn disabled_task_lists_stay_literal() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse("- [x] done")?;
+        let mut parser = Parser::new(tokens);
+        parser.set_config(ParseConfig {
+            task_lists: false,
+            ..ParseConfig::default()
+        });
+
+        assert_eq!(
+            parser.parse()?,
+            vec![Node::List {
+                ordered: false,
+                tight: true,
+                items: vec![ListItem {
+                    inline: vec![Inline::Text("[x] done".into())],
+                    children: Vec::new(),
+                    checked: None,
+                }],
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn superscript_and_subscript() -> Result<()> {
+        assert_eq!(
+            parse("x^2^")?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("x".into()),
+                Inline::Superscript(vec![Inline::Text("2".into())]),
+            ])]
+        );
+        assert_eq!(
+            parse("H~2~O")?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("H".into()),
+                Inline::Subscript(vec![Inline::Text("2".into())]),
+                Inline::Text("O".into()),
+            ])]
+        );
+        // unbalanced and spaced markers stay literal
+        assert_eq!(
+            parse("2^10 and 3^4")?,
+            vec![Node::Paragraph(vec![Inline::Text("2^10 and 3^4".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sections_mode() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse("## a\n\npara\n\n## b\n\nmore")?;
+        let mut parser = Parser::new(tokens);
+        parser.set_config(ParseConfig {
+            sections: true,
+            ..ParseConfig::default()
+        });
+
+        assert_eq!(
+            parser.parse()?,
+            vec![
+                Node::Section {
+                    level: 2,
+                    heading: vec![Inline::Text("a".into())],
+                    slug: "a".into(),
+                    children: vec![Node::Paragraph(vec![Inline::Text("para".into())])],
+                    collapsed: false,
+                },
+                Node::Section {
+                    level: 2,
+                    heading: vec![Inline::Text("b".into())],
+                    slug: "b".into(),
+                    children: vec![Node::Paragraph(vec![Inline::Text("more".into())])],
+                    collapsed: false,
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mixed_nested_lists() -> Result<()> {
+        assert_eq!(
+            parse("1. a\n  - b\n  - c\n2. d")?,
+            vec![Node::List {
+                ordered: true,
+                tight: true,
+                items: vec![
+                    ListItem {
+                        inline: vec![Inline::Text("a".into())],
+                        children: vec![Node::List {
+                            ordered: false,
+                            tight: true,
+                            items: vec![
+                                ListItem {
+                                    inline: vec![Inline::Text("b".into())],
+                                    children: Vec::new(),
+                                    checked: None,
+                                },
+                                ListItem {
+                                    inline: vec![Inline::Text("c".into())],
+                                    children: Vec::new(),
+                                    checked: None,
+                                },
+                            ],
+                        }],
+                        checked: None,
+                    },
+                    ListItem {
+                        inline: vec![Inline::Text("d".into())],
+                        children: Vec::new(),
+                        checked: None,
+                    },
+                ],
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn marker_switch_starts_new_list() -> Result<()> {
+        assert_eq!(
+            parse("- a\n1. b")?,
+            vec![
+                Node::List {
+                    ordered: false,
+                    tight: true,
+                    items: vec![ListItem {
+                        inline: vec![Inline::Text("a".into())],
+                        children: Vec::new(),
+                        checked: None,
+                    }],
+                },
+                Node::List {
+                    ordered: true,
+                    tight: true,
+                    items: vec![ListItem {
+                        inline: vec![Inline::Text("b".into())],
+                        children: Vec::new(),
+                        checked: None,
+                    }],
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "emoji")]
+    #[test]
+    fn emoji_shortcodes() -> Result<()> {
+        assert_eq!(
+            parse("hi :smile: there")?,
+            vec![Node::Paragraph(vec![Inline::Text("hi 😄 there".into())])]
+        );
+        // unknown codes and lone colons stay literal
+        assert_eq!(
+            parse(":not_a_real_code:")?,
+            vec![Node::Paragraph(vec![Inline::Text(
+                ":not_a_real_code:".into()
+            )])]
+        );
+        assert_eq!(
+            parse("ratio 1:2")?,
+            vec![Node::Paragraph(vec![Inline::Text("ratio 1:2".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_and_blank_input() -> Result<()> {
+        assert_eq!(parse("")?, Vec::<Node>::new());
+        assert_eq!(parse("\n")?, Vec::<Node>::new());
+        assert_eq!(parse("   ")?, Vec::<Node>::new());
+        assert_eq!(parse("   \n\n")?, Vec::<Node>::new());
+
+        Ok(())
+    }
+
+    #[test]
+    fn debug_tree_output() -> Result<()> {
+        let nodes = parse("# Title\n\n- one\n  - two")?;
+        assert_eq!(
+            super::debug_tree(&nodes),
+            concat!(
+                "Heading(1)\n",
+                "  Text(\"Title\")\n",
+                "List(unordered)\n",
+                "  Item\n",
+                "    Text(\"one\")\n",
+                "    List(unordered)\n",
+                "      Item\n",
+                "        Text(\"two\")\n",
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn math_spans() -> Result<()> {
+        assert_eq!(
+            parse("the energy $E = mc^2$ famously")?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("the energy ".into()),
+                Inline::Math("E = mc^2".into()),
+                Inline::Text(" famously".into()),
+            ])]
+        );
+        assert_eq!(
+            parse("$$\nx^2 + y^2 = z^2\n$$")?,
+            vec![Node::MathBlock("x^2 + y^2 = z^2".into())]
+        );
+        // an unbalanced `$` stays literal
+        assert_eq!(
+            parse("costs $5")?,
+            vec![Node::Paragraph(vec![Inline::Text("costs $5".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn indented_code_block() -> Result<()> {
+        assert_eq!(
+            parse("    let x = 1;\n    x + 1")?,
+            vec![Node::CodeBlock {
+                lang: None,
+                body: "let x = 1;\nx + 1\n".into(),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn indent_inside_list_is_continuation() -> Result<()> {
+        assert_eq!(
+            parse("- item\n    continued")?,
+            vec![Node::List {
+                ordered: false,
+                tight: true,
+                items: vec![ListItem {
+                    inline: vec![Inline::Text("item continued".into())],
+                    children: Vec::new(),
+                    checked: None,
+                }],
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn nesting_depth_capped() -> Result<()> {
+        let md = format!("{} deep", ">".repeat(10_000));
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse(&md)?;
+        let mut parser = Parser::new(tokens);
+
+        let (nodes, warnings) = parser.parse_with_warnings()?;
+        assert!(!nodes.is_empty());
+        assert_eq!(
+            warnings,
+            vec![Warning {
+                span: None,
+                message: "maximum nesting depth 100 exceeded".into(),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn definition_list() -> Result<()> {
+        assert_eq!(
+            parse("term\n: meaning")?,
+            vec![Node::DefinitionList(vec![(
+                vec![Inline::Text("term".into())],
+                vec![vec![Inline::Text("meaning".into())]],
+            )])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn definition_list_two_defs() -> Result<()> {
+        assert_eq!(
+            parse("term\n: first\n: second")?,
+            vec![Node::DefinitionList(vec![(
+                vec![Inline::Text("term".into())],
+                vec![
+                    vec![Inline::Text("first".into())],
+                    vec![Inline::Text("second".into())],
+                ],
+            )])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn single_footnote() -> Result<()> {
+        assert_eq!(
+            parse("text[^1]\n\n[^1]: the note")?,
+            vec![
+                Node::Paragraph(vec![
+                    Inline::Text("text".into()),
+                    Inline::FootnoteRef("1".into()),
+                ]),
+                Node::FootnoteDefs(vec![(
+                    "1".into(),
+                    vec![Inline::Text("the note".into())]
+                )]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn footnotes_number_by_first_reference() -> Result<()> {
+        assert_eq!(
+            parse("a[^b] c[^a]\n\n[^a]: first\n[^b]: second")?,
+            vec![
+                Node::Paragraph(vec![
+                    Inline::Text("a".into()),
+                    Inline::FootnoteRef("1".into()),
+                    Inline::Text(" c".into()),
+                    Inline::FootnoteRef("2".into()),
+                ]),
+                Node::FootnoteDefs(vec![
+                    ("1".into(), vec![Inline::Text("second".into())]),
+                    ("2".into(), vec![Inline::Text("first".into())]),
+                ]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn undefined_footnote_warns() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse("text[^nope]")?;
+        let mut parser = Parser::new(tokens);
+
+        let (nodes, warnings) = parser.parse_with_warnings()?;
+        assert_eq!(
+            nodes,
+            vec![Node::Paragraph(vec![Inline::Text("text[^nope]".into())])]
+        );
+        assert_eq!(
+            warnings,
+            vec![Warning {
+                span: None,
+                message: "undefined footnote reference `nope`".into(),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn dangling_reference_warns() -> Result<()> {
+        let md = "see [text][missing] here";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse_spanned(md)?;
+        let mut parser = Parser::new_spanned(tokens);
+
+        let (nodes, warnings) = parser.parse_with_warnings()?;
+        assert_eq!(
+            nodes,
+            vec![Node::Paragraph(vec![Inline::Text(
+                "see [text][missing] here".into()
+            )])]
+        );
+        assert_eq!(
+            warnings,
+            vec![Warning {
+                span: Some(4..19),
+                message: "dangling reference link `missing`".into(),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mentions() -> Result<()> {
+        assert_eq!(
+            parse("hi @alice")?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("hi ".into()),
+                Inline::Mention("alice".into()),
+            ])]
+        );
+        // an `@` inside a word is not a mention
+        assert_eq!(
+            parse("a@b.com")?,
+            vec![Node::Paragraph(vec![Inline::Text("a@b.com".into())])]
+        );
+        // a trailing `@` stays literal
+        assert_eq!(
+            parse("bye @")?,
+            vec![Node::Paragraph(vec![Inline::Text("bye @".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn not_an_autolink() -> Result<()> {
+        assert_eq!(
+            parse("<not a link>")?,
+            vec![Node::Paragraph(vec![Inline::Text("<not a link>".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn simple_blockquote() -> Result<()> {
+        assert_eq!(
+            parse("> quoted text")?,
+            vec![Node::BlockQuote(vec![Node::Paragraph(vec![Inline::Text(
+                "quoted text".into()
+            )])])]
+        );
+        // lazy continuation joins the quote's paragraph
+        assert_eq!(
+            parse("> quoted\ntext")?,
+            vec![Node::BlockQuote(vec![Node::Paragraph(vec![
+                Inline::Text("quoted".into()),
+                Inline::SoftBreak,
+                Inline::Text("text".into()),
+            ])])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn blockquote_with_list() -> Result<()> {
+        assert_eq!(
+            parse("> - a\n> - b")?,
+            vec![Node::BlockQuote(vec![Node::List {
+                ordered: false,
+                tight: true,
+                items: vec![item("a"), item("b")],
+            }])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn loose_list() -> Result<()> {
+        assert_eq!(
+            parse("- a\n\n- b")?,
+            vec![Node::List {
+                ordered: false,
+                tight: false,
+                items: vec![item("a"), item("b")],
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn multi_paragraph_item() -> Result<()> {
+        assert_eq!(
+            parse("- first\n\n  second")?,
+            vec![Node::List {
+                ordered: false,
+                tight: false,
+                items: vec![ListItem {
+                    inline: vec![Inline::Text("first".into())],
+                    children: vec![Node::Paragraph(vec![Inline::Text("second".into())])],
+                    checked: None,
+                }],
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn prose_punctuation_stays_text() -> Result<()> {
+        assert_eq!(
+            parse("Really? 50% {sure}")?,
+            vec![Node::Paragraph(vec![Inline::Text("Really? 50% {sure}".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn yaml_frontmatter() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse("---\ntitle: hi\ndraft: true\n---\n\nbody")?;
+        let mut parser = Parser::new(tokens);
+
+        let (frontmatter, nodes) = parser.parse_with_frontmatter()?;
+        assert_eq!(
+            frontmatter,
+            Some(Frontmatter {
+                format: FrontmatterFormat::Yaml,
+                raw: "title: hi\ndraft: true".into(),
+            })
+        );
+        assert_eq!(
+            nodes,
+            vec![Node::Paragraph(vec![Inline::Text("body".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn toml_frontmatter() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse("+++\ntitle = \"hi\"\n+++\n\nbody")?;
+        let mut parser = Parser::new(tokens);
+
+        let (frontmatter, nodes) = parser.parse_with_frontmatter()?;
+        assert_eq!(
+            frontmatter,
+            Some(Frontmatter {
+                format: FrontmatterFormat::Toml,
+                raw: "title = \"hi\"".into(),
+            })
+        );
+        assert_eq!(
+            nodes,
+            vec![Node::Paragraph(vec![Inline::Text("body".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn leading_rule_is_not_frontmatter() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse("---\n\ntext")?;
+        let mut parser = Parser::new(tokens);
+
+        let (frontmatter, nodes) = parser.parse_with_frontmatter()?;
+        assert_eq!(frontmatter, None);
+        assert_eq!(
+            nodes,
+            vec![
+                Node::Rule,
+                Node::Paragraph(vec![Inline::Text("text".into())]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn callout_kinds() -> Result<()> {
+        assert_eq!(
+            parse("> [!NOTE]\n> text")?,
+            vec![Node::Callout {
+                kind: CalloutKind::Note,
+                title: "Note".into(),
+                children: vec![Node::Paragraph(vec![Inline::Text("text".into())])],
+            }]
+        );
+        assert_eq!(
+            parse("> [!WARNING]\n> careful")?,
+            vec![Node::Callout {
+                kind: CalloutKind::Warning,
+                title: "Warning".into(),
+                children: vec![Node::Paragraph(vec![Inline::Text("careful".into())])],
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_callout_is_generic() -> Result<()> {
+        assert_eq!(
+            parse("> [!FOO]\n> bar")?,
+            vec![Node::Callout {
+                kind: CalloutKind::Generic,
+                title: "Foo".into(),
+                children: vec![Node::Paragraph(vec![Inline::Text("bar".into())])],
+            }]
+        );
+        // a marker with trailing content stays a plain quote
+        assert_eq!(
+            parse("> [!NOTE] inline")?,
+            vec![Node::BlockQuote(vec![Node::Paragraph(vec![Inline::Text(
+                "[!NOTE] inline".into()
+            )])])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_blockquote() -> Result<()> {
+        assert_eq!(
+            parse("> a\n>> b")?,
+            vec![Node::BlockQuote(vec![
+                Node::Paragraph(vec![Inline::Text("a".into())]),
+                Node::BlockQuote(vec![Node::Paragraph(vec![Inline::Text("b".into())])]),
+            ])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn code_span_beats_emphasis() -> Result<()> {
+        assert_eq!(
+            parse("*a `code*` b*")?,
+            vec![Node::Paragraph(vec![Inline::Italic(vec![
+                Inline::Text("a ".into()),
+                Inline::Code("code*".into()),
+                Inline::Text(" b".into()),
+            ])])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mismatched_delimiter_runs() -> Result<()> {
+        // the shorter run wins, the leftover delimiter stays literal
+        assert_eq!(
+            parse("**foo*")?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("*".into()),
+                Inline::Italic(vec![Inline::Text("foo".into())]),
+            ])]
+        );
+        assert_eq!(
+            parse("*foo**")?,
+            vec![Node::Paragraph(vec![
+                Inline::Italic(vec![Inline::Text("foo".into())]),
+                Inline::Text("*".into()),
+            ])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn intraword_asterisk_opens() -> Result<()> {
+        // `*` may open emphasis mid-word where `_` may not
+        assert_eq!(
+            parse("foo*bar*")?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("foo".into()),
+                Inline::Italic(vec![Inline::Text("bar".into())]),
+            ])]
+        );
+        assert_eq!(
+            parse("foo_bar_")?,
+            vec![Node::Paragraph(vec![Inline::Text("foo_bar_".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_source_spans() -> Result<()> {
+        let md = "# Title\n\nsecond block\n\n- item";
+
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse_spanned(md)?;
+        let mut parser = Parser::new_spanned(tokens);
+        let nodes = parser.parse_spanned()?;
+
+        assert_eq!(nodes.len(), 3);
+        let (node, span) = &nodes[1];
+        assert_eq!(
+            *node,
+            Node::Paragraph(vec![Inline::Text("second block".into())])
+        );
+        let span = span.clone().expect("spanned tokens produce block spans");
+        assert_eq!(&md[span], "second block");
+
+        // parsers built without spans still parse, just without ranges
+        let mut lexer = Lexer::new();
+        let mut parser = Parser::new(lexer.parse(md)?);
+        assert!(parser.parse_spanned()?.iter().all(|(_, s)| s.is_none()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn aligned_table() -> Result<()> {
+        let md = "| a | b | c |\n| :-- | :-: | --: |\n| 1 | 2 | 3 |";
+        assert_eq!(
+            parse(md)?,
+            vec![Node::Table {
+                align: vec![Align::Left, Align::Center, Align::Right],
+                header: vec![
+                    vec![Inline::Text("a".into())],
+                    vec![Inline::Text("b".into())],
+                    vec![Inline::Text("c".into())],
+                ],
+                rows: vec![vec![
+                    vec![Inline::Text("1".into())],
+                    vec![Inline::Text("2".into())],
+                    vec![Inline::Text("3".into())],
+                ]],
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn ragged_table_row() -> Result<()> {
+        let md = "| a | b |\n| --- | --- |\n| only |";
+        assert_eq!(
+            parse(md)?,
+            vec![Node::Table {
+                align: vec![Align::Left, Align::Left],
+                header: vec![
+                    vec![Inline::Text("a".into())],
+                    vec![Inline::Text("b".into())],
+                ],
+                rows: vec![vec![vec![Inline::Text("only".into())], Vec::new()]],
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn escapes() -> Result<()> {
+        assert_eq!(
+            parse("\\*not bold\\*")?,
+            vec![Node::Paragraph(vec![Inline::Text("*not bold*".into())])]
+        );
+        assert_eq!(
+            parse("\\[x\\]")?,
+            vec![Node::Paragraph(vec![Inline::Text("[x]".into())])]
+        );
+        assert_eq!(
+            parse("a \\\\ b")?,
+            vec![Node::Paragraph(vec![Inline::Text("a \\ b".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn non_escapable_backslash() -> Result<()> {
+        // only ASCII punctuation can be escaped
+        assert_eq!(
+            parse("\\q")?,
+            vec![Node::Paragraph(vec![Inline::Text("\\q".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reference_links() -> Result<()> {
+        let link = |href: &str| {
+            vec![Node::Paragraph(vec![Inline::Link {
+                text: vec![Inline::Text("site".into())],
+                href: href.into(),
+                title: None,
+                anchor: None,
+            }])]
+        };
+
+        // full reference
+        assert_eq!(parse("[site][a]\n\n[a]: http://x.com")?, link("http://x.com"));
+        // collapsed and shortcut references fall back to the text as id
+        assert_eq!(parse("[site][]\n\n[site]: /s")?, link("/s"));
+        assert_eq!(parse("[site]\n\n[site]: /s")?, link("/s"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn dangling_reference() -> Result<()> {
+        // an unresolved reference stays literal
+        assert_eq!(
+            parse("[site][nope]")?,
+            vec![Node::Paragraph(vec![Inline::Text("[site][nope]".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn html_entities() -> Result<()> {
+        assert_eq!(
+            parse("a &amp; b")?,
+            vec![Node::Paragraph(vec![Inline::Text("a & b".into())])]
+        );
+        assert_eq!(
+            parse("&#39;x&#39;")?,
+            vec![Node::Paragraph(vec![Inline::Text("'x'".into())])]
+        );
+        assert_eq!(
+            parse("&#x41;")?,
+            vec![Node::Paragraph(vec![Inline::Text("A".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn bare_ampersand() -> Result<()> {
+        // unknown entities and a lone `&` stay literal
+        assert_eq!(
+            parse("&bogus; a & b")?,
+            vec![Node::Paragraph(vec![Inline::Text("&bogus; a & b".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn code_spans() -> Result<()> {
+        assert_eq!(
+            parse("run `x` now")?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("run ".into()),
+                Inline::Code("x".into()),
+                Inline::Text(" now".into()),
+            ])]
+        );
+        // a double-backtick span can hold single backticks, and the
+        // contents stay literal
+        assert_eq!(
+            parse("``a `b` *c*``")?,
+            vec![Node::Paragraph(vec![Inline::Code("a `b` *c*".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unclosed_code_span() -> Result<()> {
+        assert_eq!(
+            parse("a `b")?,
+            vec![Node::Paragraph(vec![Inline::Text("a `b".into())])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn soft_break_joins_paragraph() -> Result<()> {
+        assert_eq!(
+            parse("a\nb")?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("a".into()),
+                Inline::SoftBreak,
+                Inline::Text("b".into()),
+            ])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn thematic_breaks() -> Result<()> {
+        assert_eq!(parse("---")?, vec![Node::Rule]);
+        assert_eq!(parse("***")?, vec![Node::Rule]);
+        assert_eq!(parse("_ _ _")?, vec![Node::Rule]);
+        // a spaced rule wins over a list marker
+        assert_eq!(parse("- - -")?, vec![Node::Rule]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn setext_beats_rule() -> Result<()> {
+        // directly below text `---` underlines a setext heading, after a
+        // blank line it is a rule
+        assert_eq!(
+            parse("Title\n---")?,
+            vec![Node::Heading {
+                level: 2,
+                inline: vec![Inline::Text("Title".into())],
+                slug: "title".into(),
+            }]
+        );
+        assert_eq!(
+            parse("Title\n\n---")?,
+            vec![
+                Node::Paragraph(vec![Inline::Text("Title".into())]),
+                Node::Rule,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn blank_lines_split_paragraphs() -> Result<()> {
+        let two = vec![
+            Node::Paragraph(vec![Inline::Text("a".into())]),
+            Node::Paragraph(vec![Inline::Text("b".into())]),
+        ];
+
+        assert_eq!(parse("a\n\nb")?, two);
+        // extra blank lines collapse into the same boundary
+        assert_eq!(parse("a\n\n\nb")?, two);
+        assert_eq!(parse("a\n\n\n\nb")?, two);
+
+        Ok(())
+    }
+
+    #[test]
+    fn hard_breaks() -> Result<()> {
+        // two trailing spaces
+        assert_eq!(
+            parse("a  \nb")?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("a".into()),
+                Inline::HardBreak,
+                Inline::Text("b".into()),
+            ])]
+        );
+        // trailing backslash
+        assert_eq!(
+            parse("a\\\nb")?,
+            vec![Node::Paragraph(vec![
+                Inline::Text("a".into()),
+                Inline::HardBreak,
+                Inline::Text("b".into()),
+            ])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn heading_and_paragraph() -> Result<()> {
+        let md = "# Title\nbody";
+
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse(md)?;
+
+        let mut parser = Parser::new(tokens);
+        let nodes = parser.parse()?;
+
+        assert_eq!(
+            nodes,
+            vec![
+                Node::Heading {
+                    level: 1,
+                    inline: vec![Inline::Text("Title".into())],
+                    slug: "title".into(),
+                },
+                Node::Paragraph(vec![Inline::Text("body".into())]),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+//! the built-in `:name:` shortcode table, a hand-picked set of the
+//! codes chat tools use most, kept small on purpose — this is not a
+//! full emoji database
+
+/// the emoji for `name`, `None` for unknown shortcodes so the caller
+/// can keep the text literal
+pub fn lookup(name: &str) -> Option<&'static str> {
+    let emoji = match name {
+        "smile" => "😄",
+        "grin" => "😁",
+        "laughing" => "😆",
+        "wink" => "😉",
+        "cry" => "😢",
+        "sob" => "😭",
+        "thinking" => "🤔",
+        "heart" => "❤️",
+        "broken_heart" => "💔",
+        "thumbsup" | "+1" => "👍",
+        "thumbsdown" | "-1" => "👎",
+        "clap" => "👏",
+        "wave" => "👋",
+        "eyes" => "👀",
+        "tada" => "🎉",
+        "rocket" => "🚀",
+        "fire" => "🔥",
+        "sparkles" => "✨",
+        "star" => "⭐",
+        "zap" => "⚡",
+        "bug" => "🐛",
+        "warning" => "⚠️",
+        "question" => "❓",
+        "check" | "white_check_mark" => "✅",
+        "x" => "❌",
+        "100" => "💯",
+        _ => return None,
+    };
+    Some(emoji)
+}
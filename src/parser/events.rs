@@ -0,0 +1,268 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::error::Error;
+
+use super::{
+    ast::{self, Align, Inline, Node},
+    lexer::Lexer,
+};
+
+/// the kind of container an `Event::Start`/`Event::End` pair delimits
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Tag {
+    Paragraph,
+    Heading(usize),
+    List { ordered: bool },
+    Item,
+    CodeBlock { lang: Option<String> },
+    BlockQuote,
+    Table { align: Vec<Align> },
+    TableHead,
+    TableRow,
+    TableCell,
+    Emphasis,
+    Strong,
+    Link {
+        href: String,
+        title: Option<String>,
+    },
+}
+
+/// one step of a pull-parse, mirrors the pulldown-cmark shape so
+/// consumers can render incrementally
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Event {
+    Start(Tag),
+    End(Tag),
+    Text(String),
+    Code(String),
+    /// raw html passed through uninterpreted
+    Html(String),
+    /// raw latex from a `$...$` span or `$$` block
+    Math(String),
+    Rule,
+    SoftBreak,
+    HardBreak,
+}
+
+/// pull-parser over a markdown input, yields `Event`s instead of a tree
+#[derive(Debug)]
+pub struct Parser {
+    events: alloc::vec::IntoIter<Event>,
+}
+
+impl Parser {
+    pub fn new(input: &str) -> Result<Parser, Error> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse(input)?;
+
+        let mut parser = ast::Parser::new(tokens);
+        let nodes = parser.parse()?;
+
+        let mut events: Vec<Event> = Vec::new();
+        for node in &nodes {
+            push_node(node, &mut events);
+        }
+        Ok(Parser {
+            events: events.into_iter(),
+        })
+    }
+}
+
+impl Iterator for Parser {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.events.next()
+    }
+}
+
+fn push_node(node: &Node, events: &mut Vec<Event>) {
+    match node {
+        Node::Heading { level, inline, .. } => {
+            events.push(Event::Start(Tag::Heading(*level)));
+            push_inline(inline, events);
+            events.push(Event::End(Tag::Heading(*level)));
+        }
+        Node::Paragraph(inline) => {
+            events.push(Event::Start(Tag::Paragraph));
+            push_inline(inline, events);
+            events.push(Event::End(Tag::Paragraph));
+        }
+        Node::List { ordered, items, .. } => {
+            let tag = Tag::List { ordered: *ordered };
+            events.push(Event::Start(tag.clone()));
+            for item in items {
+                events.push(Event::Start(Tag::Item));
+                push_inline(&item.inline, events);
+                for child in &item.children {
+                    push_node(child, events);
+                }
+                events.push(Event::End(Tag::Item));
+            }
+            events.push(Event::End(tag));
+        }
+        Node::CodeBlock { lang, body } => {
+            let tag = Tag::CodeBlock { lang: lang.clone() };
+            events.push(Event::Start(tag.clone()));
+            events.push(Event::Code(body.clone()));
+            events.push(Event::End(tag));
+        }
+        Node::Table {
+            align,
+            header,
+            rows,
+        } => {
+            let tag = Tag::Table {
+                align: align.clone(),
+            };
+            events.push(Event::Start(tag.clone()));
+            events.push(Event::Start(Tag::TableHead));
+            push_row(header, events);
+            events.push(Event::End(Tag::TableHead));
+            for row in rows {
+                events.push(Event::Start(Tag::TableRow));
+                push_row(row, events);
+                events.push(Event::End(Tag::TableRow));
+            }
+            events.push(Event::End(tag));
+        }
+        Node::BlockQuote(inner) => {
+            events.push(Event::Start(Tag::BlockQuote));
+            for child in inner {
+                push_node(child, events);
+            }
+            events.push(Event::End(Tag::BlockQuote));
+        }
+        // a callout flattens to a quote whose first paragraph is the
+        // title, event consumers wanting the kind can parse the tree
+        Node::Callout {
+            title, children, ..
+        } => {
+            events.push(Event::Start(Tag::BlockQuote));
+            events.push(Event::Start(Tag::Paragraph));
+            events.push(Event::Text(title.clone()));
+            events.push(Event::End(Tag::Paragraph));
+            for child in children {
+                push_node(child, events);
+            }
+            events.push(Event::End(Tag::BlockQuote));
+        }
+        Node::DefinitionList(entries) => {
+            for (term, defs) in entries {
+                events.push(Event::Start(Tag::Paragraph));
+                push_inline(term, events);
+                events.push(Event::End(Tag::Paragraph));
+                for def in defs {
+                    events.push(Event::Start(Tag::Paragraph));
+                    events.push(Event::Text(String::from(": ")));
+                    push_inline(def, events);
+                    events.push(Event::End(Tag::Paragraph));
+                }
+            }
+        }
+        Node::FootnoteDefs(defs) => {
+            for (label, inline) in defs {
+                events.push(Event::Start(Tag::Paragraph));
+                events.push(Event::Text(alloc::format!("[{label}]: ")));
+                push_inline(inline, events);
+                events.push(Event::End(Tag::Paragraph));
+            }
+        }
+        Node::Section {
+            level,
+            heading,
+            children,
+            ..
+        } => {
+            events.push(Event::Start(Tag::Heading(*level)));
+            push_inline(heading, events);
+            events.push(Event::End(Tag::Heading(*level)));
+            for child in children {
+                push_node(child, events);
+            }
+        }
+        Node::Html(raw) => events.push(Event::Html(raw.clone())),
+        Node::MathBlock(body) => events.push(Event::Math(body.clone())),
+        Node::Rule => events.push(Event::Rule),
+    }
+}
+
+fn push_row(cells: &[Vec<Inline>], events: &mut Vec<Event>) {
+    for cell in cells {
+        events.push(Event::Start(Tag::TableCell));
+        push_inline(cell, events);
+        events.push(Event::End(Tag::TableCell));
+    }
+}
+
+fn push_inline(inline: &[Inline], events: &mut Vec<Event>) {
+    for node in inline {
+        match node {
+            Inline::Text(text) => events.push(Event::Text(text.clone())),
+            Inline::SoftBreak => events.push(Event::SoftBreak),
+            Inline::HardBreak => events.push(Event::HardBreak),
+            Inline::Bold(inner) => {
+                events.push(Event::Start(Tag::Strong));
+                push_inline(inner, events);
+                events.push(Event::End(Tag::Strong));
+            }
+            Inline::Italic(inner) => {
+                events.push(Event::Start(Tag::Emphasis));
+                push_inline(inner, events);
+                events.push(Event::End(Tag::Emphasis));
+            }
+            Inline::Code(code) => events.push(Event::Code(code.clone())),
+            Inline::Mention(name) => events.push(Event::Text(alloc::format!("@{name}"))),
+            Inline::FootnoteRef(label) => {
+                events.push(Event::Text(alloc::format!("[{label}]")))
+            }
+            // the placeholder form, event consumers wanting the real
+            // image can match on the src themselves
+            Inline::Image { alt, .. } => events.push(Event::Text(alt.clone())),
+            // no dedicated tag, the raised/lowered content flattens into
+            // the surrounding text for event consumers
+            Inline::Superscript(inner) | Inline::Subscript(inner) => push_inline(inner, events),
+            Inline::Html(raw) => events.push(Event::Html(raw.clone())),
+            Inline::Math(body) => events.push(Event::Math(body.clone())),
+            Inline::Link {
+                text, href, title, ..
+            } => {
+                let tag = Tag::Link {
+                    href: href.clone(),
+                    title: title.clone(),
+                };
+                events.push(Event::Start(tag.clone()));
+                push_inline(text, events);
+                events.push(Event::End(tag));
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use anyhow::{Ok, Result};
+
+    use super::{Event, Parser, Tag};
+
+    #[test]
+    fn event_sequence() -> Result<()> {
+        let events = Parser::new("# Hi\n\npara")?.collect::<Vec<Event>>();
+
+        assert_eq!(
+            events,
+            vec![
+                Event::Start(Tag::Heading(1)),
+                Event::Text("Hi".into()),
+                Event::End(Tag::Heading(1)),
+                Event::Start(Tag::Paragraph),
+                Event::Text("para".into()),
+                Event::End(Tag::Paragraph),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -1,14 +1,33 @@
-use std::{fmt::Display, u8};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Display;
+use core::ops::Range;
 
 use crate::error::Error;
 
 const INDENT_CHARS: &[u8; 65] =
     b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ1234567890,\"\'";
 
+/// `INDENT_CHARS` as a byte-indexed lookup table, the per-byte
+/// membership test on the ASCII fast path is O(1) instead of a scan
+const INDENT_TABLE: [bool; 256] = {
+    let mut table = [false; 256];
+    let mut i = 0;
+    while i < INDENT_CHARS.len() {
+        table[INDENT_CHARS[i] as usize] = true;
+        i += 1;
+    }
+    table
+};
+
+/// a single lexed token, text-bearing variants borrow their slice of the
+/// input so re-lexing a large document does not allocate per run, see
+/// `into_owned` for detaching a token from the buffer
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
-pub enum Token {
+pub enum Token<'a> {
     Heading(usize),
-    Indent(String),
+    Indent(Cow<'a, str>),
 
     WhiteSpace,
     Tab,
@@ -24,6 +43,7 @@ pub enum Token {
     RightParen,
     LeftAngle,
     RightAngle,
+    BlockQuote,
 
     Dot,
     Dash,
@@ -36,17 +56,40 @@ pub enum Token {
     Slash,
     Colon,
     SemiColon,
+    Pipe,
+    Tilde,
+    Bang,
+    Ampersand,
+    At,
+    Caret,
+    Dollar,
+    Question,
+    /// any remaining printable ascii punctuation byte (`%`, `{`, `}`),
+    /// kept as itself so ordinary prose never lexes as `Illegal`
+    Punct(u8),
+
+    Rule(char, usize),
+    OrderedMarker(usize),
+    CodeBlock {
+        lang: Option<Cow<'a, str>>,
+        body: Cow<'a, str>,
+    },
 
     Illegal(u8),
 }
 
-impl Display for Token {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "Token -> ")?;
         let simple: &str = match self {
             Token::Heading(i) => return write!(f, "Heading: #{}", i),
             Token::Indent(s) => return write!(f, "Indent: {} ", s),
             Token::Illegal(s) => return write!(f, "Illegal: {} ", s),
+            Token::Rule(c, n) => return write!(f, "Rule: {}x{}", c, n),
+            Token::OrderedMarker(n) => return write!(f, "OrderedMarker: {}", n),
+            Token::CodeBlock { lang, body } => {
+                return write!(f, "CodeBlock: {:?} {} ", lang, body)
+            }
 
             Token::WhiteSpace => "WhiteSpace",
             Token::Tab => "Tab",
@@ -60,6 +103,7 @@ impl Display for Token {
             Token::RightParen => "RightParen",
             Token::LeftAngle => "LeftAngle",
             Token::RightAngle => "RightAngle",
+            Token::BlockQuote => "BlockQuote",
 
             Token::Dot => "Dot",
             Token::Dash => "Dash",
@@ -72,70 +116,552 @@ impl Display for Token {
             Token::Colon => "Colon",
             Token::SemiColon => "SemiColon",
             Token::Slash => "Slash",
+            Token::Pipe => "Pipe",
+            Token::Tilde => "Tilde",
+            Token::Bang => "Bang",
+            Token::Ampersand => "Ampersand",
+            Token::At => "At",
+            Token::Caret => "Caret",
+            Token::Dollar => "Dollar",
+            Token::Question => "Question",
+            Token::Punct(b) => return write!(f, "Punct: {}", *b as char),
         };
         write!(f, "{simple}")
     }
 }
 
-impl Token {
+impl Token<'_> {
     pub fn is_end(&self) -> bool {
         if (*self == Token::Eof) | (*self == Token::HardBreak) {
             return true;
         }
         false
     }
+
+    /// the level of a `Token::Heading`, `None` for every other token,
+    /// saves callers a pattern-match when comparing heading depths
+    pub fn heading_level(&self) -> Option<usize> {
+        match self {
+            Token::Heading(level) => Some(*level),
+            _ => None,
+        }
+    }
+
+    /// clone any borrowed text into the token so it outlives the lexed
+    /// buffer, an owned token can be stored or sent across threads
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::Indent(s) => Token::Indent(Cow::Owned(s.into_owned())),
+            Token::CodeBlock { lang, body } => Token::CodeBlock {
+                lang: lang.map(|lang| Cow::Owned(lang.into_owned())),
+                body: Cow::Owned(body.into_owned()),
+            },
+            Token::Heading(n) => Token::Heading(n),
+            Token::Rule(c, n) => Token::Rule(c, n),
+            Token::OrderedMarker(n) => Token::OrderedMarker(n),
+            Token::Illegal(b) => Token::Illegal(b),
+            Token::Punct(b) => Token::Punct(b),
+            Token::WhiteSpace => Token::WhiteSpace,
+            Token::Tab => Token::Tab,
+            Token::SoftBreak => Token::SoftBreak,
+            Token::HardBreak => Token::HardBreak,
+            Token::Eof => Token::Eof,
+            Token::LeftSquare => Token::LeftSquare,
+            Token::RightSquare => Token::RightSquare,
+            Token::LeftParen => Token::LeftParen,
+            Token::RightParen => Token::RightParen,
+            Token::LeftAngle => Token::LeftAngle,
+            Token::RightAngle => Token::RightAngle,
+            Token::BlockQuote => Token::BlockQuote,
+            Token::Dot => Token::Dot,
+            Token::Dash => Token::Dash,
+            Token::Equal => Token::Equal,
+            Token::Plus => Token::Plus,
+            Token::Asterisk => Token::Asterisk,
+            Token::Undersocre => Token::Undersocre,
+            Token::BackTick => Token::BackTick,
+            Token::BackSlash => Token::BackSlash,
+            Token::Slash => Token::Slash,
+            Token::Colon => Token::Colon,
+            Token::SemiColon => Token::SemiColon,
+            Token::Pipe => Token::Pipe,
+            Token::Tilde => Token::Tilde,
+            Token::Bang => Token::Bang,
+            Token::Ampersand => Token::Ampersand,
+            Token::At => Token::At,
+            Token::Caret => Token::Caret,
+            Token::Dollar => Token::Dollar,
+            Token::Question => Token::Question,
+        }
+    }
+
+    /// the number of source bytes the token covers, matching what
+    /// `detokenize` writes back for it so span arithmetic can walk a
+    /// token slice without the original input
+    pub fn byte_len(&self) -> usize {
+        match self {
+            Token::Heading(n) => *n,
+            Token::Rule(c, n) => c.len_utf8() * n,
+            Token::Indent(s) => s.len(),
+            Token::OrderedMarker(n) => {
+                // the digits plus the trailing dot
+                let mut digits = 1;
+                let mut rest = n / 10;
+                while rest > 0 {
+                    digits += 1;
+                    rest /= 10;
+                }
+                digits + 1
+            }
+            Token::CodeBlock { lang, body } => {
+                // the opening fence, optional language, newline, body
+                // and closing fence
+                3 + lang.as_deref().map(str::len).unwrap_or(0) + 1 + body.len() + 3
+            }
+            Token::Eof => 0,
+            _ => 1,
+        }
+    }
+}
+
+/// byte and line/column position of a token in the original input
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
 }
 
+/// a `Token` together with the `Span` it was read from
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Spanned<T> {
+    pub token: T,
+    pub span: Span,
+}
+
+/// the output of `Lexer::relex`, `relexed` counts the tokens that had
+/// to be produced fresh instead of reused
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Relexed<'a> {
+    pub tokens: Vec<Spanned<Token<'a>>>,
+    pub relexed: usize,
+}
+
+/// configuration for `Lexer::with_config`, extends the built-in set of
+/// word characters so dialect-specific bytes like `@` stay inside
+/// `Indent` runs instead of lexing as their own token
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct LexerConfig {
+    pub word_chars: Vec<u8>,
+    /// coalesce non-delimiter punctuation into the surrounding prose
+    /// run so `world.` lexes as one `Indent` instead of `Indent` + `Dot`
+    pub coarse: bool,
+    /// reject bytes outside the known set with `Error::IllegalByte`
+    /// instead of absorbing them into the text run, for validators
+    /// that want real-world prose flagged rather than tolerated
+    pub strict: bool,
+}
+
+/// the lexer borrows its input for `'a`, every token it produces borrows
+/// from the same buffer instead of copying it
 #[allow(dead_code)]
 #[derive(Debug)]
-pub struct Lexer {
+pub struct Lexer<'a> {
     position: usize,
     read_position: usize,
     ch: u8,
-    input: Vec<u8>,
+    line: usize,
+    col: usize,
+    at_line_start: bool,
+    lossy: bool,
+    /// set once per input, a verified-ASCII document skips the
+    /// multibyte handling in `read_indent`
+    ascii_only: bool,
+    config: LexerConfig,
+    input: &'a [u8],
+}
+
+impl Default for Lexer<'_> {
+    fn default() -> Self {
+        Lexer::new()
+    }
 }
 
 #[allow(dead_code)]
-impl Lexer {
-    pub fn new() -> Lexer {
+impl<'a> Lexer<'a> {
+    pub fn new() -> Lexer<'a> {
         return Lexer {
             position: 0,
             read_position: 0,
             ch: 0,
-            input: "".into(),
+            line: 1,
+            col: 0,
+            at_line_start: true,
+            lossy: false,
+            ascii_only: true,
+            config: LexerConfig::default(),
+            input: &[],
         };
     }
 
-    pub fn parse<T: ToString>(&mut self, input: &T) -> Result<Vec<Token>, Error> {
-        // BUG: format!("\n{}") is needed becuze it skips first line
-        self.input = format!("\n{}", input.to_string()).into();
-        // self.input = input.to_string().into();
+    /// like `new` but with extra word characters merged into the
+    /// indent-run logic, useful for dialects like `@user` mentions
+    pub fn with_config(config: LexerConfig) -> Lexer<'a> {
+        let mut lexer = Lexer::new();
+        lexer.config = config;
+        lexer
+    }
+
+    /// set up a lexer over `input` directly, pair with `tokenize`,
+    /// avoids the copy `parse` style entry points can force on callers
+    /// that already hold a `&str`
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'a str) -> Lexer<'a> {
+        Self::from_bytes(input.as_bytes())
+    }
 
-        let mut tokens: Vec<Token> = Vec::new();
-        self.next_token()?;
-        while !(self.position >= self.input.len()) {
-            tokens.push(self.next_token()?);
+    /// like `from_str` but over raw bytes, e.g. a file read without a
+    /// utf-8 check, unrecognized bytes surface as errors (or
+    /// `Token::Illegal` under `parse_lossy`) as usual
+    pub fn from_bytes(input: &'a [u8]) -> Lexer<'a> {
+        let mut lexer = Lexer::new();
+        lexer.input = input;
+        lexer.ascii_only = input.is_ascii();
+        lexer
+    }
+
+    /// read `path` and lex its contents, `buf` receives the file's
+    /// bytes so the zero-copy tokens have owned storage to point into,
+    /// a failed read surfaces as `Error::Io`
+    #[cfg(feature = "std")]
+    pub fn parse_file(
+        path: impl AsRef<std::path::Path>,
+        buf: &'a mut Vec<u8>,
+    ) -> Result<Vec<Token<'a>>, Error> {
+        *buf = std::fs::read(path)?;
+        let mut lexer = Lexer::from_bytes(buf);
+        lexer.tokenize()
+    }
+
+    /// lex the input this lexer was constructed over
+    pub fn tokenize(&mut self) -> Result<Vec<Token<'a>>, Error> {
+        self.rewind();
+        let mut tokens: Vec<Token<'a>> = Vec::new();
+        loop {
+            let sp = self.next_token()?;
+            let done = sp.token == Token::Eof;
+            tokens.push(sp.token);
+            if done {
+                break;
+            }
         }
         Ok(tokens)
     }
 
-    fn next_token(&mut self) -> Result<Token, Error> {
+    pub fn parse(&mut self, input: &'a str) -> Result<Vec<Token<'a>>, Error> {
+        Ok(self
+            .parse_spanned(input)?
+            .into_iter()
+            .map(|sp| sp.token)
+            .collect())
+    }
+
+    /// like `parse` but keeps the `Span` of every token so errors and
+    /// rendered cells can be mapped back to the source
+    pub fn parse_spanned(&mut self, input: &'a str) -> Result<Vec<Spanned<Token<'a>>>, Error> {
+        self.tokens_spanned(input).collect()
+    }
+
+    /// like `parse` but unrecognized bytes are kept in the stream as
+    /// `Token::Illegal` instead of failing the whole parse
+    pub fn parse_lossy(&mut self, input: &'a str) -> Result<Vec<Token<'a>>, Error> {
+        self.lossy = true;
+        let res = self.parse(input);
+        self.lossy = false;
+        res
+    }
+
+    /// streaming version of `parse`, tokens are produced lazily so a
+    /// consumer can stop early without lexing the whole input
+    pub fn tokens(&mut self, input: &'a str) -> impl Iterator<Item = Result<Token<'a>, Error>> + '_ {
+        self.tokens_spanned(input).map(|res| res.map(|sp| sp.token))
+    }
+
+    /// like `tokens` but keeps the `Span` of every token
+    pub fn tokens_spanned(&mut self, input: &'a str) -> Tokens<'_, 'a> {
+        self.reset(input);
+        Tokens {
+            lexer: self,
+            done: false,
+        }
+    }
+
+    /// re-lex `new_src` after an edit, reusing the tokens of the
+    /// unchanged lines from a previous `parse_spanned` of `old_src`,
+    /// `edit` is the byte range in `new_src` holding the replacement
+    /// text, only the lines touching it are tokenized again
+    pub fn relex(
+        &mut self,
+        old_tokens: &[Spanned<Token<'_>>],
+        old_src: &str,
+        new_src: &'a str,
+        edit: Range<usize>,
+    ) -> Result<Relexed<'a>, Error> {
+        let delta = new_src.len() as isize - old_src.len() as isize;
+
+        // reuse whole lines strictly before the edit, a break's kind
+        // depends on the byte after it so the boundary is exclusive
+        let mut prefix_len = 0;
+        let mut resume_at = 0;
+        let mut resume_line = 1;
+        for (i, sp) in old_tokens.iter().enumerate() {
+            if sp.span.end >= edit.start {
+                break;
+            }
+            if matches!(sp.token, Token::SoftBreak | Token::HardBreak) {
+                prefix_len = i + 1;
+                resume_at = sp.span.end;
+                resume_line = sp.span.line + 1;
+            }
+        }
+
+        // reuse whole lines after the edit, starting past the first soft
+        // break that survives the edit untouched, the break itself is
+        // re-lexed because its column sits on the changed line
+        let old_edit_end = (edit.end as isize - delta).max(0) as usize;
+        let suffix_start = old_tokens.iter().enumerate().position(|(i, sp)| {
+            i >= prefix_len
+                && sp.span.start >= old_edit_end.max(resume_at)
+                && sp.token == Token::SoftBreak
+        });
+        let (region, suffix) = match suffix_start {
+            Some(b) => {
+                let stop_new = (old_tokens[b].span.end as isize + delta) as usize;
+                (&new_src[resume_at..stop_new], &old_tokens[b + 1..])
+            }
+            None => (&new_src[resume_at..], &[][..]),
+        };
+        // a fence can hide line boundaries, fall back to a full lex
+        if region.contains("```") {
+            let tokens = self.parse_spanned(new_src)?;
+            let relexed = tokens.len();
+            return Ok(Relexed { tokens, relexed });
+        }
+
+        let mut tokens: Vec<Spanned<Token<'a>>> = old_tokens[..prefix_len]
+            .iter()
+            .map(|sp| Self::retarget(sp, old_src, new_src, 0, 0))
+            .collect();
+
+        let mut relexed = 0;
+        let mut sub = Lexer::with_config(self.config.clone());
+        for sp in sub.parse_spanned(region)? {
+            if sp.token == Token::Eof && !suffix.is_empty() {
+                break;
+            }
+            relexed += 1;
+            tokens.push(Spanned {
+                token: sp.token,
+                span: Span {
+                    start: sp.span.start + resume_at,
+                    end: sp.span.end + resume_at,
+                    line: sp.span.line + resume_line - 1,
+                    col: sp.span.col,
+                },
+            });
+        }
+
+        if let Some(first) = suffix.first() {
+            let line_shift = (resume_line + region.matches('\n').count()) as isize
+                - first.span.line as isize;
+            tokens.extend(
+                suffix
+                    .iter()
+                    .map(|sp| Self::retarget(sp, old_src, new_src, delta, line_shift)),
+            );
+        }
+        Ok(Relexed { tokens, relexed })
+    }
+
+    /// re-point a reused token at `new_src`, the bytes it covers are
+    /// identical so only offsets and line numbers move
+    fn retarget<'b>(
+        sp: &Spanned<Token<'_>>,
+        old_src: &str,
+        new_src: &'b str,
+        delta: isize,
+        line_shift: isize,
+    ) -> Spanned<Token<'b>> {
+        let reslice = |s: &str| -> &'b str {
+            let off = s.as_ptr() as usize - old_src.as_ptr() as usize;
+            let off = (off as isize + delta) as usize;
+            &new_src[off..off + s.len()]
+        };
+        let token = match &sp.token {
+            Token::Indent(s) => Token::Indent(reslice(s).into()),
+            Token::CodeBlock { lang, body } => Token::CodeBlock {
+                lang: lang.as_deref().map(|lang| reslice(lang).into()),
+                body: reslice(body).into(),
+            },
+            Token::Heading(n) => Token::Heading(*n),
+            Token::Rule(c, n) => Token::Rule(*c, *n),
+            Token::OrderedMarker(n) => Token::OrderedMarker(*n),
+            Token::Illegal(b) => Token::Illegal(*b),
+            Token::WhiteSpace => Token::WhiteSpace,
+            Token::Tab => Token::Tab,
+            Token::SoftBreak => Token::SoftBreak,
+            Token::HardBreak => Token::HardBreak,
+            Token::Eof => Token::Eof,
+            Token::LeftSquare => Token::LeftSquare,
+            Token::RightSquare => Token::RightSquare,
+            Token::LeftParen => Token::LeftParen,
+            Token::RightParen => Token::RightParen,
+            Token::LeftAngle => Token::LeftAngle,
+            Token::RightAngle => Token::RightAngle,
+            Token::BlockQuote => Token::BlockQuote,
+            Token::Dot => Token::Dot,
+            Token::Dash => Token::Dash,
+            Token::Equal => Token::Equal,
+            Token::Plus => Token::Plus,
+            Token::Asterisk => Token::Asterisk,
+            Token::Undersocre => Token::Undersocre,
+            Token::BackTick => Token::BackTick,
+            Token::BackSlash => Token::BackSlash,
+            Token::Slash => Token::Slash,
+            Token::Colon => Token::Colon,
+            Token::SemiColon => Token::SemiColon,
+            Token::Pipe => Token::Pipe,
+            Token::Tilde => Token::Tilde,
+            Token::Bang => Token::Bang,
+            Token::Ampersand => Token::Ampersand,
+            Token::At => Token::At,
+            Token::Caret => Token::Caret,
+            Token::Dollar => Token::Dollar,
+            Token::Question => Token::Question,
+            Token::Punct(b) => Token::Punct(*b),
+        };
+        Spanned {
+            token,
+            span: Span {
+                start: (sp.span.start as isize + delta) as usize,
+                end: (sp.span.end as isize + delta) as usize,
+                line: (sp.span.line as isize + line_shift) as usize,
+                col: sp.span.col,
+            },
+        }
+    }
+
+    fn reset(&mut self, input: &'a str) {
+        self.input = input.as_bytes();
+        self.ascii_only = self.input.is_ascii();
+        self.rewind();
+    }
+
+    fn rewind(&mut self) {
+        // reset the cursor so the same Lexer can be reused for a new input
+        self.position = 0;
+        self.read_position = 0;
+        self.ch = 0;
+        self.line = 1;
+        self.col = 0;
+        self.at_line_start = true;
+        // prime `ch` with the first byte so the first line is not skipped
+        self.read_char();
+    }
+
+    fn next_token(&mut self) -> Result<Spanned<Token<'a>>, Error> {
+        let start = self.position;
+        let line = self.line;
+        let col = self.col;
+        let at_line_start = self.at_line_start;
+        self.at_line_start = false;
+
         let tk = match self.ch {
+            // configured word chars win over the built-in single-char
+            // tokens so e.g. `@user` stays one `Indent` run
+            ch if self.config.word_chars.contains(&ch) => {
+                let tk = self.read_indent();
+                return Ok(self.spanned(tk, start, line, col));
+            }
+            // coarse mode folds non-delimiter punctuation into the
+            // surrounding prose run, line-leading bytes keep their
+            // block roles (ordered markers, definition colons)
+            ch if self.config.coarse
+                && !(at_line_start && (ch.is_ascii_digit() || ch == b':'))
+                && self.coarse_joins(ch) =>
+            {
+                let tk = self.read_coarse_run();
+                return Ok(self.spanned(tk, start, line, col));
+            }
             b' ' => Token::WhiteSpace,
             b'[' => Token::LeftSquare,
             b']' => Token::RightSquare,
-            b')' => Token::LeftParen,
-            b'(' => Token::RightParen,
+            b'(' => Token::LeftParen,
+            b')' => Token::RightParen,
             b'<' => Token::LeftAngle,
-            b'>' => Token::RightAngle,
+            b'>' => {
+                // a line-leading `>` is a blockquote marker, anywhere else
+                // it is a plain closing angle bracket
+                if at_line_start {
+                    Token::BlockQuote
+                } else {
+                    Token::RightAngle
+                }
+            }
+            b'-' | b'=' | b'*' if self.peek() == self.ch => {
+                let tk = self.read_run();
+                return Ok(self.spanned(tk, start, line, col));
+            }
             b'-' => Token::Dash,
             b'+' => Token::Plus,
             b'=' => Token::Equal,
-            b'#' => return Ok(self.read_heading()),
-            ch if INDENT_CHARS.contains(&ch) => return Ok(self.read_indent()),
+            b'|' => Token::Pipe,
+            b'~' => Token::Tilde,
+            b'!' => Token::Bang,
+            b'&' => Token::Ampersand,
+            b'@' => Token::At,
+            b'^' => Token::Caret,
+            b'$' => Token::Dollar,
+            b'?' => Token::Question,
+            b'%' | b'{' | b'}' => Token::Punct(self.ch),
+            b'#' => {
+                let tk = self.read_heading();
+                return Ok(self.spanned(tk, start, line, col));
+            }
+            ch if at_line_start && ch.is_ascii_digit() => {
+                let tk = match self.read_ordered_marker() {
+                    Some(tk) => tk,
+                    None => self.read_indent(),
+                };
+                return Ok(self.spanned(tk, start, line, col));
+            }
+            ch if INDENT_CHARS.contains(&ch) || !ch.is_ascii() => {
+                let tk = self.read_indent();
+                return Ok(self.spanned(tk, start, line, col));
+            }
             b'\0' => Token::Eof,
             b'\n' => {
+                self.at_line_start = true;
+                if matches!(self.peek(), b'\n' | b'\r') {
+                    Token::HardBreak
+                } else {
+                    Token::SoftBreak
+                }
+            }
+            b'\r' => {
+                self.at_line_start = true;
                 if self.peek() == b'\n' {
+                    // CRLF is a single line ending, fold the pair into
+                    // one break token
+                    self.read_char();
+                } else {
+                    // a bare CR still ends the line, `read_char` only
+                    // tracks LF so bump the position here
+                    self.line += 1;
+                    self.col = 0;
+                }
+                if matches!(self.peek(), b'\n' | b'\r') {
                     Token::HardBreak
                 } else {
                     Token::SoftBreak
@@ -144,26 +670,67 @@ impl Lexer {
 
             b'.' => Token::Dot,
             b'_' => Token::Undersocre,
-            b'`' => Token::BackTick,
+            b'`' => {
+                if self.peek_n(1) == b'`' && self.peek_n(2) == b'`' {
+                    let tk = self.read_code_block();
+                    return Ok(self.spanned(tk, start, line, col));
+                }
+                Token::BackTick
+            }
             b'\\' => Token::BackSlash,
             b'*' => Token::Asterisk,
             b':' => Token::Colon,
             b';' => Token::SemiColon,
             b'/' => Token::Slash,
             b'\t' => Token::Tab,
+            // the lenient default absorbs anything unrecognized into
+            // the text run so prose never aborts the lex
+            _ if !self.config.strict && !self.lossy => {
+                let pos = self.position;
+                self.read_char();
+                let tk = Token::Indent(
+                    core::str::from_utf8(&self.input[pos..self.position.min(self.input.len())])
+                        .unwrap_or("")
+                        .into(),
+                );
+                return Ok(self.spanned(tk, start, line, col));
+            }
             _ => Token::Illegal(self.ch),
         };
 
         match tk {
-            Token::Illegal(_) => return Err(Error::LexerErr(tk.to_string())),
+            Token::Illegal(byte) if !self.lossy => {
+                return Err(Error::IllegalByte {
+                    byte,
+                    offset: start,
+                    line,
+                    col,
+                })
+            }
             _ => (),
         }
 
         self.read_char();
-        Ok(tk)
+        Ok(self.spanned(tk, start, line, col))
+    }
+
+    fn spanned(&self, token: Token<'a>, start: usize, line: usize, col: usize) -> Spanned<Token<'a>> {
+        Spanned {
+            token,
+            span: Span {
+                start,
+                end: self.position.min(self.input.len()),
+                line,
+                col,
+            },
+        }
     }
 
     fn read_char(&mut self) {
+        if self.ch == b'\n' {
+            self.line += 1;
+            self.col = 0;
+        }
         if self.read_position >= self.input.len() {
             self.ch = b'\0';
         } else {
@@ -171,25 +738,154 @@ impl Lexer {
         }
         self.position = self.read_position;
         self.read_position += 1;
+        self.col += 1;
     }
 
-    fn peek(&mut self) -> u8 {
-        if self.read_position >= self.input.len() {
-            return 0;
+    fn peek(&self) -> u8 {
+        self.peek_n(1)
+    }
+
+    /// the byte `n` positions ahead of the current one without
+    /// consuming anything, `0` past the end of the input, `peek_n(0)`
+    /// is the current byte
+    fn peek_n(&self, n: usize) -> u8 {
+        match n {
+            0 => self.ch,
+            n => *self
+                .input
+                .get(self.read_position + n - 1)
+                .unwrap_or(&0),
+        }
+    }
+
+    fn read_indent(&mut self) -> Token<'a> {
+        let pos = self.position;
+        if self.ascii_only && self.config.word_chars.is_empty() {
+            // a verified-ASCII input only needs the table lookup
+            while INDENT_TABLE[self.ch as usize] {
+                self.read_char()
+            }
+            return Token::Indent(
+                core::str::from_utf8(&self.input[pos..self.position])
+                    .unwrap_or("")
+                    .into(),
+            );
+        }
+        // non-ASCII bytes are part of a multibyte UTF-8 char, keep them in
+        // the run so text like "café" or "日本語" survives intact
+        while INDENT_CHARS.contains(&self.ch)
+            || !self.ch.is_ascii()
+            || self.config.word_chars.contains(&self.ch)
+        {
+            self.read_char()
+        }
+        // the run borrows the input directly, its boundaries always fall
+        // on ASCII delimiters so the slice stays valid UTF-8
+        Token::Indent(core::str::from_utf8(&self.input[pos..self.position]).unwrap_or("").into())
+    }
+
+    /// whether `ch` joins a coarse prose run, structural markdown
+    /// delimiters and whitespace never do
+    fn coarse_joins(&self, ch: u8) -> bool {
+        INDENT_CHARS.contains(&ch)
+            || !ch.is_ascii() && ch != 0
+            || self.config.word_chars.contains(&ch)
+            || matches!(ch, b'.' | b';' | b':' | b'?' | b'@' | b'&' | b'^' | b'/')
+            || (ch == b'!' && self.peek() != b'[')
+    }
+
+    /// the coarse-mode counterpart of `read_indent`
+    fn read_coarse_run(&mut self) -> Token<'a> {
+        let pos = self.position;
+        while self.coarse_joins(self.ch) {
+            self.read_char()
+        }
+        Token::Indent(core::str::from_utf8(&self.input[pos..self.position]).unwrap_or("").into())
+    }
+
+    /// a digit run at the start of a line followed by `.` or `)` is an
+    /// ordered-list marker, anything else falls back to `read_indent`
+    fn read_ordered_marker(&mut self) -> Option<Token<'a>> {
+        let mut end = self.position;
+        while end < self.input.len() && self.input[end].is_ascii_digit() {
+            end += 1;
+        }
+        if end >= self.input.len() || !(self.input[end] == b'.' || self.input[end] == b')') {
+            return None;
+        }
+        let number = String::from_utf8_lossy(&self.input[self.position..end])
+            .parse::<usize>()
+            .ok()?;
+        // consume the digits and the trailing delimiter
+        while self.position <= end {
+            self.read_char()
+        }
+        Some(Token::OrderedMarker(number))
+    }
+
+    /// consume a ``` fence, its optional language tag and everything up to
+    /// the closing fence (or EOF) without tokenizing the body as markdown
+    fn read_code_block(&mut self) -> Token<'a> {
+        // the opening fence
+        for _ in 0..3 {
+            self.read_char();
+        }
+        // an optional language tag fills the rest of the fence line
+        let lang_start = self.position;
+        while self.ch != b'\n' && self.ch != b'\0' {
+            self.read_char();
+        }
+        let lang = core::str::from_utf8(&self.input[lang_start..self.position])
+            .unwrap_or("")
+            .trim();
+        let lang = if lang.is_empty() { None } else { Some(lang) };
+        if self.ch == b'\n' {
+            self.read_char();
+        }
+
+        // scan ahead for a line-leading closing fence, an unterminated
+        // fence runs to EOF
+        let body_start = self.position;
+        let mut body_end = self.input.len();
+        let mut i = body_start;
+        while i + 3 <= self.input.len() {
+            let line_start = i == 0 || self.input[i - 1] == b'\n';
+            if line_start && &self.input[i..i + 3] == b"```" {
+                body_end = i;
+                break;
+            }
+            i += 1;
+        }
+        let body = core::str::from_utf8(&self.input[body_start..body_end]).unwrap_or("");
+
+        let target = if body_end == self.input.len() {
+            body_end
         } else {
-            self.input[self.read_position]
+            // the closing fence is consumed along with the body
+            body_end + 3
+        };
+        while self.position < target {
+            self.read_char();
+        }
+
+        Token::CodeBlock {
+            lang: lang.map(Cow::Borrowed),
+            body: body.into(),
         }
     }
 
-    fn read_indent(&mut self) -> Token {
+    /// consume a run of the current byte so `---`, `===` and `***` come
+    /// out as one token with the repeat count instead of singles
+    fn read_run(&mut self) -> Token<'a> {
+        let ch = self.ch;
         let pos = self.position;
-        while INDENT_CHARS.contains(&self.ch) {
+        while self.ch == ch {
             self.read_char()
         }
-        return Token::Indent(String::from_utf8_lossy(&self.input[pos..self.position]).to_string());
+        Token::Rule(ch as char, self.position - pos)
     }
 
-    fn read_heading(&mut self) -> Token {
+    fn read_heading(&mut self) -> Token<'a> {
         let pos = self.position;
         while self.ch == b'#' {
             self.read_char()
@@ -198,11 +894,109 @@ impl Lexer {
     }
 }
 
-#[cfg(test)]
+/// reconstruct the source text a token stream was lexed from, the
+/// inverse of `Lexer::parse`, the few places the lexer normalizes are
+/// not recoverable: `1)` ordered markers come back as `1.`, CRLF line
+/// endings come back as bare LF, an unterminated fence gains its
+/// closing fence and a fence language tag loses surrounding whitespace
+pub fn detokenize(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for tk in tokens {
+        match tk {
+            Token::Heading(n) => {
+                for _ in 0..*n {
+                    out.push('#');
+                }
+            }
+            Token::Indent(s) => out.push_str(s),
+            Token::Rule(c, n) => {
+                for _ in 0..*n {
+                    out.push(*c);
+                }
+            }
+            Token::OrderedMarker(n) => {
+                out.push_str(&alloc::format!("{}.", n));
+            }
+            Token::CodeBlock { lang, body } => {
+                out.push_str("```");
+                if let Some(lang) = lang {
+                    out.push_str(lang);
+                }
+                out.push('\n');
+                out.push_str(body);
+                out.push_str("```");
+            }
+            Token::Illegal(b) => out.push(*b as char),
+            Token::WhiteSpace => out.push(' '),
+            Token::Tab => out.push('\t'),
+            Token::SoftBreak | Token::HardBreak => out.push('\n'),
+            Token::Eof => (),
+            Token::LeftSquare => out.push('['),
+            Token::RightSquare => out.push(']'),
+            Token::LeftParen => out.push('('),
+            Token::RightParen => out.push(')'),
+            Token::LeftAngle => out.push('<'),
+            Token::RightAngle | Token::BlockQuote => out.push('>'),
+            Token::Dot => out.push('.'),
+            Token::Dash => out.push('-'),
+            Token::Equal => out.push('='),
+            Token::Plus => out.push('+'),
+            Token::Asterisk => out.push('*'),
+            Token::Undersocre => out.push('_'),
+            Token::BackTick => out.push('`'),
+            Token::BackSlash => out.push('\\'),
+            Token::Slash => out.push('/'),
+            Token::Colon => out.push(':'),
+            Token::SemiColon => out.push(';'),
+            Token::Pipe => out.push('|'),
+            Token::Tilde => out.push('~'),
+            Token::Bang => out.push('!'),
+            Token::Ampersand => out.push('&'),
+            Token::At => out.push('@'),
+            Token::Caret => out.push('^'),
+            Token::Dollar => out.push('$'),
+            Token::Question => out.push('?'),
+            Token::Punct(b) => out.push(*b as char),
+        }
+    }
+    out
+}
+
+/// iterator returned by `Lexer::tokens_spanned`, yields tokens until the
+/// `Token::Eof` (inclusive) or the first error
+#[derive(Debug)]
+pub struct Tokens<'l, 'a> {
+    lexer: &'l mut Lexer<'a>,
+    done: bool,
+}
+
+impl<'a> Iterator for Tokens<'_, 'a> {
+    type Item = Result<Spanned<Token<'a>>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.lexer.next_token() {
+            Result::Ok(tk) => {
+                if tk.token == Token::Eof {
+                    self.done = true;
+                }
+                Some(Ok(tk))
+            }
+            Result::Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
 mod test {
     use anyhow::{Ok, Result};
 
-    use super::{Lexer, Token};
+    use super::{detokenize, Lexer, LexerConfig, Span, Token};
 
     #[test]
     fn get_next_token() -> Result<()> {
@@ -211,15 +1005,13 @@ mod test {
 ";
 
         let tokens = vec![
-            Token::SoftBreak,
             Token::Heading(1),
             Token::WhiteSpace,
             Token::Indent("Test".into()),
             Token::WhiteSpace,
             Token::Plus,
             Token::WhiteSpace,
-            Token::Dash,
-            Token::Dash,
+            Token::Rule('-', 2),
             Token::Indent("243a,".into()),
             Token::Dot,
             Token::Indent("p".into()),
@@ -232,11 +1024,12 @@ mod test {
             Token::SoftBreak,
             Token::Indent("2".into()),
             Token::SoftBreak,
+            Token::Eof,
         ];
 
         let mut lexer = Lexer::new();
 
-        let res = lexer.parse::<&str>(&input)?;
+        let res = lexer.parse(input)?;
 
         let mut diff: Vec<(Token, Token)> = vec![];
         for (i, t) in tokens.iter().enumerate() {
@@ -252,6 +1045,813 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn fenced_code_blocks() -> Result<()> {
+        let mut lexer = Lexer::new();
+
+        let res = lexer.parse("```rust\nlet x = *a;\n```")?;
+        assert_eq!(
+            res,
+            vec![
+                Token::CodeBlock {
+                    lang: Some("rust".into()),
+                    body: "let x = *a;\n".into(),
+                },
+                Token::Eof,
+            ]
+        );
+
+        let res = lexer.parse("```\n# not a heading\n```")?;
+        assert_eq!(
+            res,
+            vec![
+                Token::CodeBlock {
+                    lang: None,
+                    body: "# not a heading\n".into(),
+                },
+                Token::Eof,
+            ]
+        );
+
+        // an unterminated fence runs to the end of the input
+        let res = lexer.parse("```\nabc")?;
+        assert_eq!(
+            res,
+            vec![
+                Token::CodeBlock {
+                    lang: None,
+                    body: "abc".into(),
+                },
+                Token::Eof,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn ordered_markers() -> Result<()> {
+        let mut lexer = Lexer::new();
+
+        let res = lexer.parse("1. item")?;
+        assert_eq!(
+            res,
+            vec![
+                Token::OrderedMarker(1),
+                Token::WhiteSpace,
+                Token::Indent("item".into()),
+                Token::Eof,
+            ]
+        );
+
+        let res = lexer.parse("42) item")?;
+        assert_eq!(
+            res,
+            vec![
+                Token::OrderedMarker(42),
+                Token::WhiteSpace,
+                Token::Indent("item".into()),
+                Token::Eof,
+            ]
+        );
+
+        // numbers inside a line are plain text
+        let res = lexer.parse("see v1.2")?;
+        assert_eq!(
+            res,
+            vec![
+                Token::Indent("see".into()),
+                Token::WhiteSpace,
+                Token::Indent("v1".into()),
+                Token::Dot,
+                Token::Indent("2".into()),
+                Token::Eof,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn punctuation_runs() -> Result<()> {
+        let input = "---\n***\n===";
+
+        let tokens = vec![
+            Token::Rule('-', 3),
+            Token::SoftBreak,
+            Token::Rule('*', 3),
+            Token::SoftBreak,
+            Token::Rule('=', 3),
+            Token::Eof,
+        ];
+
+        let mut lexer = Lexer::new();
+        let res = lexer.parse(input)?;
+
+        assert_eq!(tokens, res);
+
+        // a lone dash is still a plain Dash
+        let mut lexer = Lexer::new();
+        let res = lexer.parse("-")?;
+        assert_eq!(res, vec![Token::Dash, Token::Eof]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn illegal_byte_position() -> Result<()> {
+        let input = "ab\ncd\ne\x07f";
+
+        // the lenient default folds the stray byte into the text run
+        let mut lexer = Lexer::new();
+        let res = lexer.parse(input)?;
+        assert_eq!(
+            res,
+            vec![
+                Token::Indent("ab".into()),
+                Token::SoftBreak,
+                Token::Indent("cd".into()),
+                Token::SoftBreak,
+                Token::Indent("e".into()),
+                Token::Indent("\x07".into()),
+                Token::Indent("f".into()),
+                Token::Eof,
+            ]
+        );
+
+        let mut lexer = Lexer::with_config(LexerConfig {
+            strict: true,
+            ..LexerConfig::default()
+        });
+        let err = lexer.parse(input).unwrap_err();
+
+        assert_eq!(
+            err,
+            crate::error::Error::IllegalByte {
+                byte: 7,
+                offset: 7,
+                line: 3,
+                col: 2,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "md-to-tui error:  illegal byte 0x07 at line 3, col 2"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn lossy_parse_keeps_illegal() -> Result<()> {
+        let input = "# A\x07B";
+
+        let tokens = vec![
+            Token::Heading(1),
+            Token::WhiteSpace,
+            Token::Indent("A".into()),
+            Token::Illegal(7),
+            Token::Indent("B".into()),
+            Token::Eof,
+        ];
+
+        let mut lexer = Lexer::new();
+        let res = lexer.parse_lossy(input)?;
+
+        assert_eq!(tokens, res);
+
+        // the strict configuration still rejects the same input
+        let mut strict = Lexer::with_config(LexerConfig {
+            strict: true,
+            ..LexerConfig::default()
+        });
+        assert!(strict.parse(input).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn blockquote_marker() -> Result<()> {
+        let input = "> quoted\na > b";
+
+        let tokens = vec![
+            Token::BlockQuote,
+            Token::WhiteSpace,
+            Token::Indent("quoted".into()),
+            Token::SoftBreak,
+            Token::Indent("a".into()),
+            Token::WhiteSpace,
+            Token::RightAngle,
+            Token::WhiteSpace,
+            Token::Indent("b".into()),
+            Token::Eof,
+        ];
+
+        let mut lexer = Lexer::new();
+        let res = lexer.parse(input)?;
+
+        assert_eq!(tokens, res);
+
+        Ok(())
+    }
+
+    #[test]
+    fn streaming_tokens() -> Result<()> {
+        let input = "# Hi\nlol";
+
+        let mut lexer = Lexer::new();
+        let res = lexer
+            .tokens(input)
+            .take_while(|t| !matches!(t, std::result::Result::Ok(Token::SoftBreak)))
+            .collect::<std::result::Result<Vec<Token>, _>>()?;
+
+        assert_eq!(
+            res,
+            vec![
+                Token::Heading(1),
+                Token::WhiteSpace,
+                Token::Indent("Hi".into()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reuse_lexer() -> Result<()> {
+        let mut lexer = Lexer::new();
+
+        let first = lexer.parse("# A")?;
+        let second = lexer.parse("# B")?;
+
+        assert_eq!(
+            first,
+            vec![
+                Token::Heading(1),
+                Token::WhiteSpace,
+                Token::Indent("A".into()),
+                Token::Eof,
+            ]
+        );
+        assert_eq!(
+            second,
+            vec![
+                Token::Heading(1),
+                Token::WhiteSpace,
+                Token::Indent("B".into()),
+                Token::Eof,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn paren_tokens() -> Result<()> {
+        let input = "()";
+
+        let tokens = vec![Token::LeftParen, Token::RightParen, Token::Eof];
+
+        let mut lexer = Lexer::new();
+        let res = lexer.parse(input)?;
+
+        assert_eq!(tokens, res);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bang_image() -> Result<()> {
+        let input = "![cat](c.png)";
+
+        let tokens = vec![
+            Token::Bang,
+            Token::LeftSquare,
+            Token::Indent("cat".into()),
+            Token::RightSquare,
+            Token::LeftParen,
+            Token::Indent("c".into()),
+            Token::Dot,
+            Token::Indent("png".into()),
+            Token::RightParen,
+            Token::Eof,
+        ];
+
+        let mut lexer = Lexer::new();
+        let res = lexer.parse(input)?;
+
+        assert_eq!(tokens, res);
+
+        // a plain link must lex the same way minus the leading Bang
+        let mut lexer = Lexer::new();
+        let res = lexer.parse("[x](y)")?;
+        assert_eq!(res[0], Token::LeftSquare);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tilde_strikethrough() -> Result<()> {
+        let input = "~~gone~~";
+
+        let tokens = vec![
+            Token::Tilde,
+            Token::Tilde,
+            Token::Indent("gone".into()),
+            Token::Tilde,
+            Token::Tilde,
+            Token::Eof,
+        ];
+
+        let mut lexer = Lexer::new();
+        let res = lexer.parse(input)?;
+
+        assert_eq!(tokens, res);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pipe_table_row() -> Result<()> {
+        let input = "| a | b |
+|---|---|";
+
+        let tokens = vec![
+            Token::Pipe,
+            Token::WhiteSpace,
+            Token::Indent("a".into()),
+            Token::WhiteSpace,
+            Token::Pipe,
+            Token::WhiteSpace,
+            Token::Indent("b".into()),
+            Token::WhiteSpace,
+            Token::Pipe,
+            Token::SoftBreak,
+            Token::Pipe,
+            Token::Rule('-', 3),
+            Token::Pipe,
+            Token::Rule('-', 3),
+            Token::Pipe,
+            Token::Eof,
+        ];
+
+        let mut lexer = Lexer::new();
+        let res = lexer.parse(input)?;
+
+        assert_eq!(tokens, res);
+
+        Ok(())
+    }
+
+    #[test]
+    fn non_ascii_indent() -> Result<()> {
+        let input = "héllo 世界";
+
+        let tokens = vec![
+            Token::Indent("héllo".into()),
+            Token::WhiteSpace,
+            Token::Indent("世界".into()),
+            Token::Eof,
+        ];
+
+        let mut lexer = Lexer::new();
+        let res = lexer.parse(input)?;
+
+        assert_eq!(tokens, res);
+
+        Ok(())
+    }
+
+    #[test]
+    fn token_spans() -> Result<()> {
+        let input = "# Hi\nlol";
+
+        let mut lexer = Lexer::new();
+        let res = lexer.parse_spanned(input)?;
+
+        assert_eq!(res[0].token, Token::Heading(1));
+        assert_eq!(
+            res[0].span,
+            Span {
+                start: 0,
+                end: 1,
+                line: 1,
+                col: 1
+            }
+        );
+        assert_eq!(res[2].token, Token::Indent("Hi".into()));
+        assert_eq!(
+            res[2].span,
+            Span {
+                start: 2,
+                end: 4,
+                line: 1,
+                col: 3
+            }
+        );
+        assert_eq!(res[4].token, Token::Indent("lol".into()));
+        assert_eq!(
+            res[4].span,
+            Span {
+                start: 5,
+                end: 8,
+                line: 2,
+                col: 1
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn relex_reuses_unchanged_lines() -> Result<()> {
+        let old_src = (0..1000)
+            .map(|i| format!("line number {}\n", i))
+            .collect::<String>();
+        let mut lexer = Lexer::new();
+        let old_tokens = lexer.parse_spanned(&old_src)?;
+
+        // a one character edit in the middle of line 500
+        let offset = old_src.find("line number 500").unwrap() + 5;
+        let mut new_src = old_src.clone();
+        new_src.insert(offset, 'x');
+
+        let mut lexer = Lexer::new();
+        let relexed = lexer.relex(&old_tokens, &old_src, &new_src, offset..offset + 1)?;
+
+        let mut lexer = Lexer::new();
+        assert_eq!(relexed.tokens, lexer.parse_spanned(&new_src)?);
+        assert!(
+            relexed.relexed < 20,
+            "only the edited line should re-lex, got {}",
+            relexed.relexed
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn token_byte_lengths() -> Result<()> {
+        assert_eq!(Token::Indent("héllo".into()).byte_len(), 6);
+        assert_eq!(Token::Heading(2).byte_len(), 2);
+        assert_eq!(Token::OrderedMarker(12).byte_len(), 3);
+        assert_eq!(Token::Rule('-', 3).byte_len(), 3);
+        assert_eq!(Token::Pipe.byte_len(), 1);
+        assert_eq!(Token::Tab.byte_len(), 1);
+        assert_eq!(Token::Eof.byte_len(), 0);
+
+        // summed widths agree with the detokenized form
+        let input = "## héllo wörld 12. done\n";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse(input)?;
+        let total: usize = tokens.iter().map(Token::byte_len).sum();
+        assert_eq!(total, detokenize(&tokens).len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn heading_levels() {
+        assert_eq!(Token::Heading(1).heading_level(), Some(1));
+        assert_eq!(Token::Heading(6).heading_level(), Some(6));
+        assert_eq!(Token::Indent("text".into()).heading_level(), None);
+        assert_eq!(Token::Rule('-', 3).heading_level(), None);
+        assert_eq!(Token::Eof.heading_level(), None);
+    }
+
+    #[test]
+    fn lenient_and_strict_agree_on_prose() -> Result<()> {
+        let input = "100% sure!";
+        let expected = vec![
+            Token::Indent("100".into()),
+            Token::Punct(b'%'),
+            Token::WhiteSpace,
+            Token::Indent("sure".into()),
+            Token::Bang,
+            Token::Eof,
+        ];
+
+        let mut lexer = Lexer::new();
+        assert_eq!(lexer.parse(input)?, expected);
+
+        // the known punctuation set is fine under strict too, only
+        // bytes outside it differ between the modes
+        let mut strict = Lexer::with_config(LexerConfig {
+            strict: true,
+            ..LexerConfig::default()
+        });
+        assert_eq!(strict.parse(input)?, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn prose_punctuation_lexes() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse("Really?")?;
+        assert_eq!(
+            tokens,
+            vec![Token::Indent("Really".into()), Token::Question, Token::Eof]
+        );
+
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse("50%")?;
+        assert_eq!(
+            tokens,
+            vec![Token::Indent("50".into()), Token::Punct(b'%'), Token::Eof]
+        );
+
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse("{a}")?;
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Punct(b'{'),
+                Token::Indent("a".into()),
+                Token::Punct(b'}'),
+                Token::Eof,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn detokenize_round_trip() -> Result<()> {
+        let inputs = [
+            "# Heading\n\nsome *bold* and _nested_ text\n",
+            "- one\n- two\n1. three\n",
+            "```rust\nlet x = 1;\n```\n",
+            "> quote\n> more\n\n| a | b |\n| - | - |\n",
+            "a\tb [link](http://example.com)\n\n---\n",
+        ];
+
+        for input in inputs {
+            let mut lexer = Lexer::new();
+            let tokens = lexer.parse(input)?;
+            assert_eq!(detokenize(&tokens), input, "{input:?}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn fuzz_regressions() -> Result<()> {
+        // pathological inputs that exercise the byte-indexing edge
+        // cases, each must lex (or error out) without panicking
+        let inputs: &[&[u8]] = &[
+            b"\xE4",                     // truncated multibyte char
+            b"\xF0\x9F\x92",             // truncated emoji
+            b"\r",                       // lone CR
+            b"``",                       // fence shorter than three backticks
+            b"```\xFF",                  // fence running into invalid utf-8
+            b"999999999999999999999999.", // ordered marker overflowing usize
+            b"#\0#",                     // NUL mid-input
+        ];
+        for bytes in inputs {
+            let _ = Lexer::from_bytes(bytes).tokenize();
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn parse_file_reads_from_disk() -> Result<()> {
+        let path = std::env::temp_dir().join("md_to_tui_parse_file.md");
+        std::fs::write(&path, "# hi")?;
+
+        let mut buf = Vec::new();
+        let tokens = Lexer::parse_file(&path, &mut buf)?;
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Heading(1),
+                Token::WhiteSpace,
+                Token::Indent("hi".into()),
+                Token::Eof,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn owned_tokens_outlive_the_source() -> Result<()> {
+        let tokens: Vec<Token<'static>> = {
+            let source = String::from("# hi");
+            let mut lexer = Lexer::from_str(&source);
+            lexer
+                .tokenize()?
+                .into_iter()
+                .map(Token::into_owned)
+                .collect()
+        };
+
+        // the original buffer is gone, the owned tokens carry their text
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Heading(1),
+                Token::WhiteSpace,
+                Token::Indent("hi".into()),
+                Token::Eof,
+            ]
+        );
+        assert!(matches!(
+            &tokens[2],
+            Token::Indent(alloc::borrow::Cow::Owned(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn caret_lexes_cleanly() -> Result<()> {
+        let mut lexer = Lexer::new();
+        assert_eq!(
+            lexer.parse("a^b")?,
+            vec![
+                Token::Indent("a".into()),
+                Token::Caret,
+                Token::Indent("b".into()),
+                Token::Eof,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn peek_n_boundaries() -> Result<()> {
+        let mut lexer = Lexer::from_str("ab");
+        lexer.rewind();
+        assert_eq!(lexer.peek_n(0), b'a');
+        assert_eq!(lexer.peek_n(1), b'b');
+        // at and past the end of the input
+        assert_eq!(lexer.peek_n(2), 0);
+        assert_eq!(lexer.peek_n(100), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_and_whitespace_input() -> Result<()> {
+        let mut lexer = Lexer::new();
+        assert_eq!(lexer.parse("")?, vec![Token::Eof]);
+        assert_eq!(lexer.parse("\n")?, vec![Token::SoftBreak, Token::Eof]);
+        assert_eq!(
+            lexer.parse("  ")?,
+            vec![Token::WhiteSpace, Token::WhiteSpace, Token::Eof]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn coarse_tokenization() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let fine = lexer.parse("hello, world. foo")?;
+        assert_eq!(
+            fine,
+            vec![
+                Token::Indent("hello,".into()),
+                Token::WhiteSpace,
+                Token::Indent("world".into()),
+                Token::Dot,
+                Token::WhiteSpace,
+                Token::Indent("foo".into()),
+                Token::Eof,
+            ]
+        );
+
+        let mut lexer = Lexer::with_config(LexerConfig {
+            coarse: true,
+            ..LexerConfig::default()
+        });
+        let coarse = lexer.parse("hello, world. foo")?;
+        assert_eq!(
+            coarse,
+            vec![
+                Token::Indent("hello,".into()),
+                Token::WhiteSpace,
+                Token::Indent("world.".into()),
+                Token::WhiteSpace,
+                Token::Indent("foo".into()),
+                Token::Eof,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn configured_word_chars() -> Result<()> {
+        let mut lexer = Lexer::with_config(LexerConfig {
+            word_chars: vec![b'@'],
+            ..LexerConfig::default()
+        });
+
+        let res = lexer.parse("hi @user")?;
+        assert_eq!(
+            res,
+            vec![
+                Token::Indent("hi".into()),
+                Token::WhiteSpace,
+                Token::Indent("@user".into()),
+                Token::Eof,
+            ]
+        );
+
+        // without the config `@` is still its own token
+        let mut lexer = Lexer::new();
+        let res = lexer.parse("@user")?;
+        assert_eq!(
+            res,
+            vec![Token::At, Token::Indent("user".into()), Token::Eof]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn crlf_line_endings() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let unix = lexer.parse("# A\nB\n")?;
+
+        let mut lexer = Lexer::new();
+        assert_eq!(lexer.parse("# A\r\nB\r\n")?, unix);
+
+        // a blank CRLF line still splits paragraphs
+        let mut lexer = Lexer::new();
+        let unix = lexer.parse("A\n\nB")?;
+        let mut lexer = Lexer::new();
+        assert_eq!(lexer.parse("A\r\n\r\nB")?, unix);
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_str_matches_parse() -> Result<()> {
+        let md = "# Title\nsome *text* `code`\n- item";
+
+        let mut lexer = Lexer::new();
+        let parsed = lexer.parse(md)?;
+
+        let mut lexer = Lexer::from_str(md);
+        assert_eq!(lexer.tokenize()?, parsed);
+
+        let mut lexer = Lexer::from_bytes(md.as_bytes());
+        assert_eq!(lexer.tokenize()?, parsed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn borrowed_slices() -> Result<()> {
+        let input = "# Hi\nsome text\n```rust\nlet x = 1;\n```";
+
+        let mut lexer = Lexer::new();
+        let res = lexer.parse(input)?;
+
+        // every text-bearing token points back into the input buffer
+        // instead of holding a copy
+        let range = input.as_ptr() as usize..input.as_ptr() as usize + input.len();
+        for tk in &res {
+            match tk {
+                Token::Indent(s) => {
+                    assert!(range.contains(&(s.as_ptr() as usize)), "copied: {s:?}");
+                }
+                Token::CodeBlock { lang, body } => {
+                    assert_eq!(lang.as_deref(), Some("rust"));
+                    assert_eq!(*body, "let x = 1;\n");
+                    assert!(range.contains(&(body.as_ptr() as usize)));
+                }
+                _ => (),
+            }
+        }
+        assert_eq!(res[2], Token::Indent("Hi".into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn lex_large_document() -> Result<()> {
+        // a 10k-line document re-lexed without per-run allocations, this
+        // mostly guards against accidental quadratic scans
+        let input = "# heading\nsome *plain* text line\n".repeat(5_000);
+
+        let mut lexer = Lexer::new();
+        let res = lexer.parse(&input)?;
+
+        assert_eq!(res.last(), Some(&Token::Eof));
+        assert!(res.len() > 10_000);
+
+        Ok(())
+    }
+
     #[test]
     fn dummy() {
         let text = r"
@@ -265,7 +1865,7 @@ lol
 
         let mut lexer = Lexer::new();
 
-        let res = lexer.parse::<&str>(&text);
+        let res = lexer.parse(text);
 
         print!("{:?}", res);
 
@@ -1,3 +1,10 @@
+pub mod ast;
+#[cfg(feature = "emoji")]
+pub mod emoji;
+pub mod events;
 pub mod lexer;
+#[cfg(feature = "std")]
 pub mod parser;
+#[cfg(feature = "std")]
 pub mod parser_helpers;
+pub mod visit;
@@ -26,19 +26,19 @@ use super::{
 // }
 
 #[derive(Debug, PartialEq, Clone, Default)]
-pub struct Parser {
-    pub input: Vec<Token>,
+pub struct Parser<'a> {
+    pub input: Vec<Token<'a>>,
     pub style: MdStyle,
 
     pub position: usize,
     pub read_position: usize,
-    pub token: Token,
+    pub token: Token<'a>,
 
     pub lines: Vec<Line<'static>>,
 }
 
-impl Parser {
-    pub fn new(input: Vec<Token>, style: Option<MdStyle>) -> Parser {
+impl<'a> Parser<'a> {
+    pub fn new(input: Vec<Token<'a>>, style: Option<MdStyle>) -> Parser<'a> {
         // info!("created new Parser");
 
         let style = match style {
@@ -78,7 +78,7 @@ impl Parser {
         Ok(lines)
     }
 
-    fn next_line<'a>(&mut self) -> Result<Line<'a>, Error> {
+    fn next_line(&mut self) -> Result<Line<'static>, Error> {
         let mut spans: Vec<Span> = Vec::new();
         while !self.token.is_end() && self.position >= self.input.len() {
             // info!("Is end {}", self.token.is_end());
@@ -89,7 +89,7 @@ impl Parser {
                     genarate_list_start(self.token.clone(), &self.style)
                 }
                 Token::WhiteSpace => Span::from(" "),
-                Token::Indent(i) => generate_indent(i.into(), &self.style),
+                Token::Indent(i) => generate_indent(i.clone().into_owned(), &self.style),
                 Token::Equal => {
                     if self.peek() == Token::Equal {
                         if self.peek() == Token::Equal {
@@ -108,12 +108,23 @@ impl Parser {
                     }
                     generate_indent("_".into(), &self.style)
                 }
+                Token::Rule(ch, n) => {
+                    if *n >= 3 {
+                        Span::styled(ch.to_string().repeat(*n), self.style.horizontal_rule)
+                    } else {
+                        Span::styled(ch.to_string().repeat(*n), self.style.text)
+                    }
+                }
+                Token::OrderedMarker(n) => Span::styled(format!("{}.", n), self.style.list),
+                Token::CodeBlock { body, .. } => {
+                    Span::styled(body.to_string(), self.style.backtick)
+                }
                 Token::Dot => Span::from("."),
-                Token::RightParen => Span::styled("(", self.style.link),
-                Token::LeftParen => Span::styled(")", self.style.link),
+                Token::LeftParen => Span::styled("(", self.style.link),
+                Token::RightParen => Span::styled(")", self.style.link),
                 Token::LeftSquare => Span::styled("[", self.style.link_text),
                 Token::RightSquare => Span::styled("]", self.style.link_text),
-                Token::RightAngle => Span::styled(">", self.style.blocqoutes),
+                Token::RightAngle | Token::BlockQuote => Span::styled(">", self.style.blocqoutes),
                 Token::LeftAngle => Span::styled("<", self.style.blocqoutes),
                 Token::BackTick => Span::styled("`", self.style.backtick),
                 Token::Colon => Span::styled(":", self.style.text),
@@ -145,7 +156,7 @@ impl Parser {
         self.read_position += 1;
     }
 
-    fn peek(&mut self) -> Token {
+    fn peek(&mut self) -> Token<'a> {
         if self.read_position >= self.input.len() {
             return Token::Eof;
         } else {
@@ -177,7 +188,7 @@ abc
 ";
 
         let mut lexer = Lexer::new();
-        let res = lexer.parse::<&str>(&md)?;
+        let res = lexer.parse(md)?;
 
         let mut parser = Parser::new(res, None);
         let res = parser.parse()?;
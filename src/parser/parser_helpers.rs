@@ -12,7 +12,7 @@ pub fn generate_indent(indent: String, style: &MdStyle) -> Span<'static> {
     Span::styled(indent, style.text)
 }
 
-pub fn genarate_list_start(token: Token, style: &MdStyle) -> Span<'static> {
+pub fn genarate_list_start(token: Token<'_>, style: &MdStyle) -> Span<'static> {
     match token {
         Token::Plus => Span::styled("+", style.list),
         Token::Dash => Span::styled("-", style.list),
@@ -22,7 +22,7 @@ pub fn genarate_list_start(token: Token, style: &MdStyle) -> Span<'static> {
     }
 }
 
-pub fn generate_horizontal_rule(token: Token, style: &MdStyle) -> Span<'static> {
+pub fn generate_horizontal_rule(token: Token<'_>, style: &MdStyle) -> Span<'static> {
     match token {
         Token::Equal => Span::styled("===", style.horizontal_rule),
         Token::Undersocre => Span::styled("___", style.horizontal_rule),
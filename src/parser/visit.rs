@@ -0,0 +1,385 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use super::ast::{Inline, Node, SpannedNode};
+
+/// read-only traversal over a parsed tree, implement the `visit_*`
+/// hooks you care about and hand the visitor to `walk`, every node and
+/// inline is visited in document order before its children
+pub trait Visitor {
+    fn visit_node(&mut self, _node: &Node) {}
+    fn visit_inline(&mut self, _inline: &Inline) {}
+}
+
+/// drive `visitor` over every node and inline in `nodes`
+pub fn walk(nodes: &[Node], visitor: &mut impl Visitor) {
+    for node in nodes {
+        walk_node(node, visitor);
+    }
+}
+
+fn walk_node(node: &Node, visitor: &mut impl Visitor) {
+    visitor.visit_node(node);
+    match node {
+        Node::Heading { inline, .. } | Node::Paragraph(inline) => walk_inlines(inline, visitor),
+        Node::List { items, .. } => {
+            for item in items {
+                walk_inlines(&item.inline, visitor);
+                walk(&item.children, visitor);
+            }
+        }
+        Node::Table { header, rows, .. } => {
+            for cell in header.iter().chain(rows.iter().flatten()) {
+                walk_inlines(cell, visitor);
+            }
+        }
+        Node::BlockQuote(inner) => walk(inner, visitor),
+        Node::Callout { children, .. } => walk(children, visitor),
+        Node::DefinitionList(entries) => {
+            for (term, defs) in entries {
+                walk_inlines(term, visitor);
+                for def in defs {
+                    walk_inlines(def, visitor);
+                }
+            }
+        }
+        Node::FootnoteDefs(defs) => {
+            for (_, inline) in defs {
+                walk_inlines(inline, visitor);
+            }
+        }
+        Node::Section {
+            heading, children, ..
+        } => {
+            walk_inlines(heading, visitor);
+            walk(children, visitor);
+        }
+        Node::CodeBlock { .. } | Node::Html(_) | Node::MathBlock(_) | Node::Rule => {}
+    }
+}
+
+fn walk_inlines(inline: &[Inline], visitor: &mut impl Visitor) {
+    for node in inline {
+        visitor.visit_inline(node);
+        match node {
+            Inline::Bold(inner)
+            | Inline::Italic(inner)
+            | Inline::Superscript(inner)
+            | Inline::Subscript(inner) => walk_inlines(inner, visitor),
+            Inline::Link { text, .. } => walk_inlines(text, visitor),
+            _ => {}
+        }
+    }
+}
+
+/// one link or image pulled out of a document by `collect_links`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LinkRef {
+    pub href: String,
+    /// the flattened link text, the alt text for an image
+    pub text: String,
+    pub image: bool,
+    /// byte range of the block the link appeared in, `None` when the
+    /// blocks were parsed without spans
+    pub span: Option<Range<usize>>,
+}
+
+/// every link and image in `nodes` in document order, autolinks and
+/// resolved reference links included since both are `Inline::Link` by
+/// the time they reach the tree
+pub fn collect_links(nodes: &[SpannedNode]) -> Vec<LinkRef> {
+    struct Collect {
+        span: Option<Range<usize>>,
+        out: Vec<LinkRef>,
+    }
+
+    impl Visitor for Collect {
+        fn visit_inline(&mut self, inline: &Inline) {
+            match inline {
+                Inline::Link { text, href, .. } => {
+                    let mut flat = String::new();
+                    flatten(text, &mut flat);
+                    self.out.push(LinkRef {
+                        href: href.clone(),
+                        text: flat,
+                        image: false,
+                        span: self.span.clone(),
+                    });
+                }
+                Inline::Image { alt, src } => self.out.push(LinkRef {
+                    href: src.clone(),
+                    text: alt.clone(),
+                    image: true,
+                    span: self.span.clone(),
+                }),
+                _ => {}
+            }
+        }
+    }
+
+    fn flatten(inline: &[Inline], out: &mut String) {
+        for node in inline {
+            match node {
+                Inline::Text(text) | Inline::Code(text) => out.push_str(text),
+                Inline::Bold(inner)
+                | Inline::Italic(inner)
+                | Inline::Superscript(inner)
+                | Inline::Subscript(inner) => flatten(inner, out),
+                _ => {}
+            }
+        }
+    }
+
+    let mut collect = Collect {
+        span: None,
+        out: Vec::new(),
+    };
+    for (node, span) in nodes {
+        collect.span = span.clone();
+        walk_node(node, &mut collect);
+    }
+    collect.out
+}
+
+/// rewriting traversal, children are folded before their parent so a
+/// `map_node` hook sees its content already transformed, the default
+/// hooks leave everything unchanged
+pub trait MapFold {
+    fn map_node(&mut self, node: Node) -> Node {
+        node
+    }
+    fn map_inline(&mut self, inline: Inline) -> Inline {
+        inline
+    }
+}
+
+/// rebuild `nodes` through `fold`, bottom-up
+pub fn map_fold(nodes: Vec<Node>, fold: &mut impl MapFold) -> Vec<Node> {
+    nodes
+        .into_iter()
+        .map(|node| map_node(node, fold))
+        .collect()
+}
+
+fn map_node(node: Node, fold: &mut impl MapFold) -> Node {
+    let node = match node {
+        Node::Heading {
+            level,
+            inline,
+            slug,
+        } => Node::Heading {
+            level,
+            inline: map_inlines(inline, fold),
+            slug,
+        },
+        Node::Paragraph(inline) => Node::Paragraph(map_inlines(inline, fold)),
+        Node::List {
+            ordered,
+            items,
+            tight,
+        } => Node::List {
+            ordered,
+            items: items
+                .into_iter()
+                .map(|mut item| {
+                    item.inline = map_inlines(item.inline, fold);
+                    item.children = map_fold(item.children, fold);
+                    item
+                })
+                .collect(),
+            tight,
+        },
+        Node::Table {
+            align,
+            header,
+            rows,
+        } => Node::Table {
+            align,
+            header: header
+                .into_iter()
+                .map(|cell| map_inlines(cell, fold))
+                .collect(),
+            rows: rows
+                .into_iter()
+                .map(|row| row.into_iter().map(|cell| map_inlines(cell, fold)).collect())
+                .collect(),
+        },
+        Node::BlockQuote(inner) => Node::BlockQuote(map_fold(inner, fold)),
+        Node::Callout {
+            kind,
+            title,
+            children,
+        } => Node::Callout {
+            kind,
+            title,
+            children: map_fold(children, fold),
+        },
+        Node::DefinitionList(entries) => Node::DefinitionList(
+            entries
+                .into_iter()
+                .map(|(term, defs)| {
+                    (
+                        map_inlines(term, fold),
+                        defs.into_iter()
+                            .map(|def| map_inlines(def, fold))
+                            .collect(),
+                    )
+                })
+                .collect(),
+        ),
+        Node::FootnoteDefs(defs) => Node::FootnoteDefs(
+            defs.into_iter()
+                .map(|(label, inline)| (label, map_inlines(inline, fold)))
+                .collect(),
+        ),
+        Node::Section {
+            level,
+            heading,
+            slug,
+            children,
+            collapsed,
+        } => Node::Section {
+            level,
+            heading: map_inlines(heading, fold),
+            slug,
+            children: map_fold(children, fold),
+            collapsed,
+        },
+        other @ (Node::CodeBlock { .. } | Node::Html(_) | Node::MathBlock(_) | Node::Rule) => {
+            other
+        }
+    };
+    fold.map_node(node)
+}
+
+fn map_inlines(inline: Vec<Inline>, fold: &mut impl MapFold) -> Vec<Inline> {
+    inline
+        .into_iter()
+        .map(|node| {
+            let node = match node {
+                Inline::Bold(inner) => Inline::Bold(map_inlines(inner, fold)),
+                Inline::Italic(inner) => Inline::Italic(map_inlines(inner, fold)),
+                Inline::Superscript(inner) => Inline::Superscript(map_inlines(inner, fold)),
+                Inline::Subscript(inner) => Inline::Subscript(map_inlines(inner, fold)),
+                Inline::Link {
+                    text,
+                    href,
+                    title,
+                    anchor,
+                } => Inline::Link {
+                    text: map_inlines(text, fold),
+                    href,
+                    title,
+                    anchor,
+                },
+                other => other,
+            };
+            fold.map_inline(node)
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use anyhow::{Ok, Result};
+
+    use crate::parser::{ast, lexer::Lexer};
+
+    use super::{map_fold, walk, Inline, MapFold, Node, Visitor};
+
+    fn parse(md: &str) -> Result<alloc::vec::Vec<Node>> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse(md)?;
+        let mut parser = ast::Parser::new(tokens);
+        Ok(parser.parse()?)
+    }
+
+    #[test]
+    fn counts_code_blocks() -> Result<()> {
+        #[derive(Default)]
+        struct Counter {
+            code_blocks: usize,
+        }
+
+        impl Visitor for Counter {
+            fn visit_node(&mut self, node: &Node) {
+                if matches!(node, Node::CodeBlock { .. }) {
+                    self.code_blocks += 1;
+                }
+            }
+        }
+
+        let nodes = parse("```\na\n```\n\n> ```\n> b\n> ```\n\npara")?;
+        let mut counter = Counter::default();
+        walk(&nodes, &mut counter);
+        assert_eq!(counter.code_blocks, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn collects_links_in_order() -> Result<()> {
+        let md = "see [a](http://a.com) and ![pic](img.png)\n\n<http://b.com>";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse(md)?;
+        let mut parser = ast::Parser::new(tokens);
+        let nodes = parser.parse_spanned()?;
+
+        let links = super::collect_links(&nodes);
+        assert_eq!(
+            links,
+            vec![
+                super::LinkRef {
+                    href: "http://a.com".into(),
+                    text: "a".into(),
+                    image: false,
+                    span: None,
+                },
+                super::LinkRef {
+                    href: "img.png".into(),
+                    text: "pic".into(),
+                    image: true,
+                    span: None,
+                },
+                super::LinkRef {
+                    href: "http://b.com".into(),
+                    text: "http://b.com".into(),
+                    image: false,
+                    span: None,
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn strips_links() -> Result<()> {
+        struct StripLinks;
+
+        impl MapFold for StripLinks {
+            fn map_inline(&mut self, inline: Inline) -> Inline {
+                match inline {
+                    Inline::Link { mut text, .. } => match text.len() {
+                        1 => text.remove(0),
+                        _ => Inline::Text(String::new()),
+                    },
+                    other => other,
+                }
+            }
+        }
+
+        let nodes = parse("see [the docs](http://example.com)")?;
+        let nodes = map_fold(nodes, &mut StripLinks);
+        assert_eq!(
+            nodes,
+            vec![Node::Paragraph(vec![
+                Inline::Text("see ".into()),
+                Inline::Text("the docs".into()),
+            ])]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1933 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+
+use crate::{
+    parser::ast::{Align, CalloutKind, Inline, ListItem, Node, SpannedNode},
+    style::style::Theme,
+};
+
+/// render parsed nodes into a ratatui `Text`, `None` uses the default
+/// `Theme`
+pub fn to_text(nodes: &[Node], theme: Option<&Theme>) -> Text<'static> {
+    let default = Theme::default();
+    let theme = theme.unwrap_or(&default);
+
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    for (block_index, node) in nodes.iter().enumerate() {
+        if block_index > 0 {
+            for _ in 0..theme.block_spacing {
+                lines.push(Line::from(String::new()));
+            }
+        }
+        match node {
+            Node::Heading { level, inline, .. } => {
+                let hstyle = heading_style(*level, theme);
+                let mut spans = vec![Span::styled(format!("{} ", "#".repeat(*level)), hstyle)];
+                spans.extend(inline_spans(inline, hstyle, theme));
+                lines.push(Line::from(spans));
+            }
+            Node::Paragraph(inline) => {
+                // hard breaks split a paragraph over several lines
+                for segment in inline.split(|i| *i == Inline::HardBreak) {
+                    lines.push(bidi_line(Line::from(inline_spans(
+                        segment, theme.text, theme,
+                    ))));
+                }
+            }
+            Node::List {
+                ordered,
+                items,
+                tight,
+            } => {
+                push_list(*ordered, items, *tight, &mut lines, theme, 0);
+            }
+            Node::CodeBlock { lang, body } => {
+                let mut code = code_lines(lang.as_deref(), body, theme);
+                if theme.code_line_numbers {
+                    code = number_code_lines(code, theme);
+                }
+                lines.extend(code);
+            }
+            Node::Table {
+                align,
+                header,
+                rows,
+            } => {
+                lines.extend(table_lines(align, header, rows, theme));
+            }
+            Node::BlockQuote(inner) => {
+                // render the quoted blocks and gutter every line, nested
+                // quotes recurse and stack their bars
+                for line in to_text(inner, Some(theme)).lines {
+                    let mut spans = vec![Span::styled(
+                        format!("{} ", theme.quote_glyph),
+                        theme.quote,
+                    )];
+                    spans.extend(line.spans);
+                    lines.push(Line::from(spans));
+                }
+            }
+            Node::Callout {
+                kind,
+                title,
+                children,
+            } => {
+                let style = callout_style(*kind, theme);
+                lines.push(Line::from(Span::styled(
+                    format!("{} {title}", theme.quote_glyph),
+                    style,
+                )));
+                // the body keeps the callout-colored gutter on every line
+                for line in to_text(children, Some(theme)).lines {
+                    let mut spans = vec![Span::styled(format!("{} ", theme.quote_glyph), style)];
+                    spans.extend(line.spans);
+                    lines.push(Line::from(spans));
+                }
+            }
+            Node::DefinitionList(entries) => {
+                for (term, defs) in entries {
+                    lines.push(Line::from(inline_spans(
+                        term,
+                        theme.text.patch(theme.bold),
+                        theme,
+                    )));
+                    for def in defs {
+                        let mut spans = vec![Span::styled(
+                            " ".repeat(theme.list_indent),
+                            theme.text,
+                        )];
+                        spans.extend(inline_spans(def, theme.text, theme));
+                        lines.push(Line::from(spans));
+                    }
+                }
+            }
+            Node::FootnoteDefs(defs) => {
+                for (label, inline) in defs {
+                    let mut spans =
+                        vec![Span::styled(format!("[{label}]: "), theme.link)];
+                    spans.extend(inline_spans(inline, theme.text, theme));
+                    lines.push(Line::from(spans));
+                }
+            }
+            Node::Section {
+                level,
+                heading,
+                children,
+                collapsed,
+                ..
+            } => {
+                let hstyle = heading_style(*level, theme);
+                let mut spans = vec![Span::styled(format!("{} ", "#".repeat(*level)), hstyle)];
+                spans.extend(inline_spans(heading, hstyle, theme));
+                lines.push(Line::from(spans));
+                if !collapsed {
+                    lines.extend(to_text(children, Some(theme)).lines);
+                }
+            }
+            Node::Html(raw) => {
+                if theme.show_html {
+                    for line in raw.lines() {
+                        lines.push(Line::from(Span::styled(line.to_string(), theme.html)));
+                    }
+                }
+            }
+            Node::MathBlock(body) => {
+                for line in body.lines() {
+                    lines.push(Line::from(Span::styled(line.to_string(), theme.math)));
+                }
+            }
+            Node::Rule => {
+                lines.push(Line::from(Span::styled(
+                    theme.rule_glyph.to_string().repeat(theme.rule_width),
+                    theme.rule,
+                )));
+            }
+        }
+    }
+    Text::from(lines)
+}
+
+/// word-wrapping behavior switches, the default wraps only on
+/// whitespace the way `to_text_wrapped` always has
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WrapConfig {
+    /// also allow a break after `-` and `/` inside a word, so long
+    /// paths and hyphenated identifiers wrap at their separators
+    /// instead of overflowing or hard-breaking mid-token
+    pub break_on_punct: bool,
+}
+
+/// like `to_text` but paragraph lines are word-wrapped to `width`
+/// columns, a `width` of zero disables wrapping
+pub fn to_text_wrapped(nodes: &[Node], theme: Option<&Theme>, width: u16) -> Text<'static> {
+    to_text_wrapped_with(nodes, theme, width, &WrapConfig::default())
+}
+
+/// like `to_text_wrapped` with explicit wrapping behavior
+pub fn to_text_wrapped_with(
+    nodes: &[Node],
+    theme: Option<&Theme>,
+    width: u16,
+    config: &WrapConfig,
+) -> Text<'static> {
+    let default = Theme::default();
+    let resolved = theme.unwrap_or(&default);
+    if width == 0 {
+        return to_text(nodes, theme);
+    }
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    for (block_index, node) in nodes.iter().enumerate() {
+        if block_index > 0 {
+            for _ in 0..resolved.block_spacing {
+                lines.push(Line::from(String::new()));
+            }
+        }
+        // quoted content wraps inside the gutter so every wrapped line
+        // keeps its bar
+        if let Node::BlockQuote(inner) = node {
+            let inner_width = width.saturating_sub(2);
+            for line in to_text_wrapped_with(inner, theme, inner_width, config).lines {
+                let mut spans = vec![Span::styled(
+                    format!("{} ", resolved.quote_glyph),
+                    resolved.quote,
+                )];
+                spans.extend(line.spans);
+                lines.push(Line::from(spans));
+            }
+            continue;
+        }
+        for line in &to_text(std::slice::from_ref(node), theme).lines {
+            // thematic breaks clip to the target width instead of wrapping
+            let is_rule = line.spans.len() == 1
+                && !line.spans[0].content.is_empty()
+                && line.spans[0].content.chars().all(|c| c == resolved.rule_glyph);
+            if is_rule {
+                lines.push(Line::from(Span::styled(
+                    resolved.rule_glyph.to_string().repeat(usize::from(width)),
+                    resolved.rule,
+                )));
+                continue;
+            }
+            lines.extend(wrap_spans(&line.spans, usize::from(width), config));
+        }
+    }
+    Text::from(lines)
+}
+
+/// reorder `line` with the unicode bidi algorithm when its paragraph
+/// direction is right-to-left and right-align it, mixed runs come out
+/// in visual order, a reordered line collapses to the style of its
+/// first span
+#[cfg(feature = "bidi")]
+fn bidi_line(line: Line<'static>) -> Line<'static> {
+    use ratatui::layout::Alignment;
+    use unicode_bidi::BidiInfo;
+
+    let content: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+    let info = BidiInfo::new(&content, None);
+    let para = match info.paragraphs.first() {
+        Some(para) if para.level.is_rtl() => para,
+        _ => return line,
+    };
+    let visual = info.reorder_line(para, para.range.clone()).into_owned();
+    let style = line.spans.first().map(|s| s.style).unwrap_or_default();
+    Line::from(Span::styled(visual, style)).alignment(Alignment::Right)
+}
+
+#[cfg(not(feature = "bidi"))]
+fn bidi_line(line: Line<'static>) -> Line<'static> {
+    line
+}
+
+/// flatten the document to one styled line for a fixed single-line slot
+/// like a status bar or list preview, block structure is dropped,
+/// whitespace collapses to single spaces and content past `width`
+/// truncates with `…`, a `width` of zero disables truncation
+pub fn to_line(nodes: &[Node], theme: Option<&Theme>, width: u16) -> Line<'static> {
+    let default = Theme::default();
+    let theme = theme.unwrap_or(&default);
+
+    let mut raw: Vec<Span<'static>> = Vec::new();
+    flat_spans(nodes, theme, &mut raw);
+
+    // collapse whitespace runs to single spaces, dropping any leading
+    // run so the line starts on a word
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut pending_space = false;
+    for span in raw {
+        let mut content = String::new();
+        for c in span.content.chars() {
+            if c.is_whitespace() {
+                pending_space = !spans.is_empty() || !content.is_empty();
+                continue;
+            }
+            if pending_space {
+                content.push(' ');
+                pending_space = false;
+            }
+            content.push(c);
+        }
+        if !content.is_empty() {
+            spans.push(Span::styled(content, span.style));
+        }
+    }
+
+    let width = usize::from(width);
+    if width == 0 {
+        return Line::from(spans);
+    }
+    let total: usize = spans.iter().map(|s| display_width(s.content.as_ref())).sum();
+    if total <= width {
+        return Line::from(spans);
+    }
+    // cut a column short of the slot and spend it on the ellipsis, the
+    // partial span keeps its style so emphasis survives truncation
+    let mut out: Vec<Span<'static>> = Vec::new();
+    let mut used = 0;
+    'spans: for span in spans {
+        let mut content = String::new();
+        for c in span.content.chars() {
+            let w = char_width(c);
+            if used + w > width.saturating_sub(1) {
+                if !content.is_empty() {
+                    out.push(Span::styled(content, span.style));
+                }
+                out.push(Span::styled("…".to_string(), theme.text));
+                break 'spans;
+            }
+            content.push(c);
+            used += w;
+        }
+        if !content.is_empty() {
+            out.push(Span::styled(content, span.style));
+        }
+    }
+    Line::from(out)
+}
+
+/// the inline content of every block in document order, blocks and
+/// table cells are separated by a space for the collapse pass to dedupe
+fn flat_spans(nodes: &[Node], theme: &Theme, spans: &mut Vec<Span<'static>>) {
+    let separate = |spans: &mut Vec<Span<'static>>| {
+        spans.push(Span::styled(" ".to_string(), theme.text));
+    };
+    for node in nodes {
+        match node {
+            Node::Heading { level, inline, .. } => {
+                spans.extend(inline_spans(inline, heading_style(*level, theme), theme));
+            }
+            Node::Paragraph(inline) => {
+                spans.extend(inline_spans(inline, theme.text, theme));
+            }
+            Node::List { items, .. } => {
+                for item in items {
+                    spans.extend(inline_spans(&item.inline, theme.text, theme));
+                    separate(spans);
+                    flat_spans(&item.children, theme, spans);
+                }
+            }
+            Node::CodeBlock { body, .. } => {
+                spans.push(Span::styled(body.clone(), theme.code));
+            }
+            Node::Table { header, rows, .. } => {
+                for cell in header.iter().chain(rows.iter().flatten()) {
+                    spans.extend(inline_spans(cell, theme.text, theme));
+                    separate(spans);
+                }
+            }
+            Node::BlockQuote(inner) => flat_spans(inner, theme, spans),
+            Node::Callout {
+                kind,
+                title,
+                children,
+            } => {
+                spans.push(Span::styled(title.clone(), callout_style(*kind, theme)));
+                separate(spans);
+                flat_spans(children, theme, spans);
+            }
+            Node::DefinitionList(entries) => {
+                for (term, defs) in entries {
+                    spans.extend(inline_spans(term, theme.text.patch(theme.bold), theme));
+                    separate(spans);
+                    for def in defs {
+                        spans.extend(inline_spans(def, theme.text, theme));
+                        separate(spans);
+                    }
+                }
+            }
+            Node::FootnoteDefs(defs) => {
+                for (_, inline) in defs {
+                    spans.extend(inline_spans(inline, theme.text, theme));
+                    separate(spans);
+                }
+            }
+            Node::Section {
+                level,
+                heading,
+                children,
+                collapsed,
+                ..
+            } => {
+                spans.extend(inline_spans(heading, heading_style(*level, theme), theme));
+                separate(spans);
+                if !collapsed {
+                    flat_spans(children, theme, spans);
+                }
+            }
+            Node::Html(raw) => {
+                if theme.show_html {
+                    spans.push(Span::styled(raw.clone(), theme.html));
+                }
+            }
+            Node::MathBlock(body) => {
+                spans.push(Span::styled(body.clone(), theme.math));
+            }
+            Node::Rule => {}
+        }
+        separate(spans);
+    }
+}
+
+/// one block's slice of the rendered rows together with the source
+/// byte range that produced it, the side table `to_text_with_map`
+/// returns so a preview can sync cursor and scroll with an editor
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CellMap {
+    /// the first rendered row the block occupies
+    pub first_row: usize,
+    /// how many rendered rows the block occupies
+    pub rows: usize,
+    /// the source byte range of the block, `None` when the tokens were
+    /// lexed without spans or the block was synthesized
+    pub span: Option<core::ops::Range<usize>>,
+}
+
+/// like `to_text` but over spanned blocks from `parse_spanned`, also
+/// returns a row → source mapping so a click on a rendered cell can
+/// jump to the source offset that produced it
+pub fn to_text_with_map(
+    nodes: &[SpannedNode],
+    theme: Option<&Theme>,
+) -> (Text<'static>, Vec<CellMap>) {
+    let default = Theme::default();
+    let resolved = theme.unwrap_or(&default);
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    let mut map: Vec<CellMap> = Vec::new();
+    for (block_index, (node, span)) in nodes.iter().enumerate() {
+        if block_index > 0 {
+            for _ in 0..resolved.block_spacing {
+                lines.push(Line::from(String::new()));
+            }
+        }
+        let block = to_text(std::slice::from_ref(node), theme);
+        map.push(CellMap {
+            first_row: lines.len(),
+            rows: block.lines.len(),
+            span: span.clone(),
+        });
+        lines.extend(block.lines);
+    }
+    (Text::from(lines), map)
+}
+
+/// the source byte offset under a rendered cell on `row`, resolved to
+/// the start of the owning block's span — every column of a row maps to
+/// the same block, `None` when the row falls outside every mapped block
+pub fn source_offset(map: &[CellMap], row: usize) -> Option<usize> {
+    map.iter()
+        .find(|cell| row >= cell.first_row && row < cell.first_row + cell.rows)
+        .and_then(|cell| cell.span.as_ref().map(|span| span.start))
+}
+
+/// render to a plain `String` with ANSI escape codes, for printing
+/// straight to a terminal without ratatui, the layout matches `to_text`
+pub fn to_ansi(nodes: &[Node], theme: Option<&Theme>) -> String {
+    ansi_string(nodes, theme, true)
+}
+
+/// like `to_ansi` but with every escape sequence stripped, the
+/// `--color=never` path of a command line tool
+pub fn to_ansi_plain(nodes: &[Node], theme: Option<&Theme>) -> String {
+    ansi_string(nodes, theme, false)
+}
+
+fn ansi_string(nodes: &[Node], theme: Option<&Theme>, color: bool) -> String {
+    let mut out = String::new();
+    for line in to_text(nodes, theme).lines {
+        for span in &line.spans {
+            let codes = if color {
+                sgr_codes(span.style)
+            } else {
+                Vec::new()
+            };
+            if codes.is_empty() {
+                out.push_str(&span.content);
+            } else {
+                out.push_str(&format!("\x1b[{}m", codes.join(";")));
+                out.push_str(&span.content);
+                out.push_str("\x1b[0m");
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// stream `to_ansi` output block by block into `out`, only one
+/// block's lines are materialized at a time so a large document can be
+/// piped to a pager without building the whole render in memory
+pub fn write_ansi(
+    nodes: &[Node],
+    theme: Option<&Theme>,
+    out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    let default = Theme::default();
+    let resolved = theme.unwrap_or(&default);
+    for (block_index, node) in nodes.iter().enumerate() {
+        if block_index > 0 {
+            for _ in 0..resolved.block_spacing {
+                out.write_all(b"\n")?;
+            }
+        }
+        for line in to_text(std::slice::from_ref(node), theme).lines {
+            for span in &line.spans {
+                let codes = sgr_codes(span.style);
+                if codes.is_empty() {
+                    out.write_all(span.content.as_bytes())?;
+                } else {
+                    write!(out, "\x1b[{}m", codes.join(";"))?;
+                    out.write_all(span.content.as_bytes())?;
+                    out.write_all(b"\x1b[0m")?;
+                }
+            }
+            out.write_all(b"\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// the SGR parameters selecting `style`, modifiers first so the
+/// sequence reads `bold;color`
+fn sgr_codes(style: Style) -> Vec<String> {
+    let mut codes: Vec<String> = Vec::new();
+    let modifiers = [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+    ];
+    for (modifier, code) in modifiers {
+        if style.add_modifier.contains(modifier) {
+            codes.push(code.to_string());
+        }
+    }
+    if let Some(color) = style.fg {
+        codes.extend(sgr_color(color, 30));
+    }
+    if let Some(color) = style.bg {
+        codes.extend(sgr_color(color, 40));
+    }
+    codes
+}
+
+/// the SGR parameters for `color`, `base` is 30 for foreground and 40
+/// for background
+fn sgr_color(color: Color, base: u8) -> Option<String> {
+    let simple = match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 60,
+        Color::LightRed => 61,
+        Color::LightGreen => 62,
+        Color::LightYellow => 63,
+        Color::LightBlue => 64,
+        Color::LightMagenta => 65,
+        Color::LightCyan => 66,
+        Color::White => 67,
+        Color::Rgb(r, g, b) => return Some(format!("{};2;{r};{g};{b}", base + 8)),
+        Color::Indexed(i) => return Some(format!("{};5;{i}", base + 8)),
+        _ => return None,
+    };
+    Some((base + simple).to_string())
+}
+
+/// like `to_text_wrapped` but only the lines in
+/// `[first_line, first_line + height)` are materialized, blocks that fall
+/// entirely outside the window are skipped after a span-free line count
+pub fn to_text_windowed(
+    nodes: &[Node],
+    theme: Option<&Theme>,
+    width: u16,
+    first_line: usize,
+    height: u16,
+) -> Text<'static> {
+    let default = Theme::default();
+    let resolved = theme.unwrap_or(&default);
+    let end = first_line.saturating_add(usize::from(height));
+
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    let mut offset = 0;
+    for (block_index, node) in nodes.iter().enumerate() {
+        if offset >= end {
+            break;
+        }
+        if block_index > 0 {
+            // the spacing rows between blocks scroll like any others
+            for _ in 0..resolved.block_spacing {
+                if offset >= first_line && offset < end {
+                    lines.push(Line::from(String::new()));
+                }
+                offset += 1;
+            }
+        }
+        let count = block_line_count(node, resolved, usize::from(width));
+        if offset + count <= first_line {
+            // the whole block scrolled off the top, skip it unrendered
+            offset += count;
+            continue;
+        }
+        let block = to_text_wrapped(std::slice::from_ref(node), theme, width);
+        for (i, line) in block.lines.into_iter().enumerate() {
+            let idx = offset + i;
+            if idx >= first_line && idx < end {
+                lines.push(line);
+            }
+        }
+        offset += count;
+    }
+    Text::from(lines)
+}
+
+/// the number of wrapped lines a block renders to, computed from plain
+/// text lengths without allocating any spans
+fn block_line_count(node: &Node, theme: &Theme, width: usize) -> usize {
+    // a rule is one line in both the plain and the wrapped render
+    if matches!(node, Node::Rule) {
+        return 1;
+    }
+    // quoted content wraps at the width inside the gutter
+    if let Node::BlockQuote(inner) = node {
+        let inner_width = if width == 0 { 0 } else { width.saturating_sub(2) };
+        let spacing = usize::from(theme.block_spacing) * inner.len().saturating_sub(1);
+        return spacing
+            + inner
+                .iter()
+                .map(|child| block_line_count(child, theme, inner_width))
+                .sum::<usize>();
+    }
+    plain_lines(node, theme)
+        .iter()
+        .map(|l| if width == 0 { 1 } else { wrap_count(l, width) })
+        .sum()
+}
+
+/// the plain text of every line a block renders to, mirrors `to_text`
+fn plain_lines(node: &Node, theme: &Theme) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    match node {
+        Node::Heading { level, inline, .. } => {
+            out.push(format!(
+                "{} {}",
+                "#".repeat(*level),
+                plain_inline(inline, theme)
+            ));
+        }
+        Node::Paragraph(inline) => {
+            for segment in inline.split(|i| *i == Inline::HardBreak) {
+                out.push(plain_inline(segment, theme));
+            }
+        }
+        Node::List {
+            ordered,
+            items,
+            tight,
+        } => {
+            push_plain_list(*ordered, items, *tight, &mut out, theme, 0);
+        }
+        Node::CodeBlock { body, .. } => {
+            if theme.code_line_numbers {
+                let width = body.lines().count().to_string().len();
+                out.extend(
+                    body.lines()
+                        .enumerate()
+                        .map(|(i, line)| format!("{:>width$} │ {line}", i + 1)),
+                );
+            } else {
+                out.extend(body.lines().map(str::to_string));
+            }
+        }
+        Node::Table {
+            align,
+            header,
+            rows,
+        } => {
+            // the grid is cheap to lay out, reuse it and drop the styles
+            for line in table_lines(align, header, rows, theme) {
+                out.push(
+                    line.spans
+                        .iter()
+                        .map(|s| s.content.as_ref())
+                        .collect::<String>(),
+                );
+            }
+        }
+        Node::BlockQuote(inner) => {
+            for line in plain_block_run(inner, theme) {
+                out.push(format!("{} {}", theme.quote_glyph, line));
+            }
+        }
+        Node::Callout {
+            title, children, ..
+        } => {
+            out.push(format!("{} {title}", theme.quote_glyph));
+            for line in plain_block_run(children, theme) {
+                out.push(format!("{} {}", theme.quote_glyph, line));
+            }
+        }
+        Node::DefinitionList(entries) => {
+            for (term, defs) in entries {
+                out.push(plain_inline(term, theme));
+                for def in defs {
+                    out.push(format!(
+                        "{}{}",
+                        " ".repeat(theme.list_indent),
+                        plain_inline(def, theme)
+                    ));
+                }
+            }
+        }
+        Node::FootnoteDefs(defs) => {
+            for (label, inline) in defs {
+                out.push(format!("[{label}]: {}", plain_inline(inline, theme)));
+            }
+        }
+        Node::Section {
+            level,
+            heading,
+            children,
+            collapsed,
+            ..
+        } => {
+            out.push(format!(
+                "{} {}",
+                "#".repeat(*level),
+                plain_inline(heading, theme)
+            ));
+            if !collapsed {
+                out.extend(plain_block_run(children, theme));
+            }
+        }
+        Node::Html(raw) => {
+            if theme.show_html {
+                out.extend(raw.lines().map(str::to_string));
+            }
+        }
+        Node::MathBlock(body) => {
+            out.extend(body.lines().map(str::to_string));
+        }
+        Node::Rule => out.push(theme.rule_glyph.to_string()),
+    }
+    out
+}
+
+/// the plain lines of a run of sibling blocks with the theme's block
+/// spacing between them, mirrors how `to_text` lays out nested content
+fn plain_block_run(nodes: &[Node], theme: &Theme) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for (block_index, child) in nodes.iter().enumerate() {
+        if block_index > 0 {
+            for _ in 0..theme.block_spacing {
+                out.push(String::new());
+            }
+        }
+        out.extend(plain_lines(child, theme));
+    }
+    out
+}
+
+/// prefix each code line with a right-aligned line number and a
+/// separator bar, the gutter width scales with the line count so the
+/// numbers stay flush, highlighted spans are untouched behind it
+fn number_code_lines(lines: Vec<Line<'static>>, theme: &Theme) -> Vec<Line<'static>> {
+    let width = lines.len().to_string().len();
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let mut spans = vec![Span::styled(
+                format!("{:>width$} │ ", i + 1),
+                theme.code_line_number,
+            )];
+            spans.extend(line.spans);
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// render a code block body, one `Line` per source line in the uniform
+/// `code` style
+#[cfg(not(feature = "highlight"))]
+fn code_lines(_lang: Option<&str>, body: &str, theme: &Theme) -> Vec<Line<'static>> {
+    body.lines()
+        .map(|line| Line::from(Span::styled(line.to_string(), theme.code)))
+        .collect()
+}
+
+/// like the plain version but bodies with a known language are
+/// highlighted through syntect using `theme.code_theme`
+#[cfg(feature = "highlight")]
+fn code_lines(lang: Option<&str>, body: &str, theme: &Theme) -> Vec<Line<'static>> {
+    use syntect::easy::HighlightLines;
+
+    let plain = |line: &str| Line::from(Span::styled(line.to_string(), theme.code));
+    let syntax = lang.and_then(|l| syntax_set().find_syntax_by_token(l));
+    let (Some(syntax), Some(hl_theme)) = (syntax, theme_set().themes.get(&theme.code_theme))
+    else {
+        return body.lines().map(plain).collect();
+    };
+
+    let mut hl = HighlightLines::new(syntax, hl_theme);
+    body.lines()
+        .map(|line| match hl.highlight_line(line, syntax_set()) {
+            Ok(regions) => Line::from(
+                regions
+                    .into_iter()
+                    .map(|(style, text)| Span::styled(text.to_string(), convert_style(style)))
+                    .collect::<Vec<_>>(),
+            ),
+            Err(_) => plain(line),
+        })
+        .collect()
+}
+
+/// the bundled syntax definitions, loaded once per process
+#[cfg(feature = "highlight")]
+fn syntax_set() -> &'static syntect::parsing::SyntaxSet {
+    static SET: std::sync::OnceLock<syntect::parsing::SyntaxSet> = std::sync::OnceLock::new();
+    SET.get_or_init(syntect::parsing::SyntaxSet::load_defaults_newlines)
+}
+
+/// the bundled highlight themes, loaded once per process
+#[cfg(feature = "highlight")]
+fn theme_set() -> &'static syntect::highlighting::ThemeSet {
+    static SET: std::sync::OnceLock<syntect::highlighting::ThemeSet> = std::sync::OnceLock::new();
+    SET.get_or_init(syntect::highlighting::ThemeSet::load_defaults)
+}
+
+/// map a syntect style onto a ratatui one, backgrounds are dropped so
+/// the widget's own background shows through
+#[cfg(feature = "highlight")]
+fn convert_style(style: syntect::highlighting::Style) -> Style {
+    use ratatui::style::{Color, Modifier};
+    use syntect::highlighting::FontStyle;
+
+    let fg = style.foreground;
+    let mut out = Style::default().fg(Color::Rgb(fg.r, fg.g, fg.b));
+    if style.font_style.contains(FontStyle::BOLD) {
+        out = out.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        out = out.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        out = out.add_modifier(Modifier::UNDERLINED);
+    }
+    out
+}
+
+/// lay out a table as a box-drawing grid, column widths come from the
+/// widest cell capped at `theme.max_col_width`
+fn table_lines(
+    align: &[Align],
+    header: &[Vec<Inline>],
+    rows: &[Vec<Vec<Inline>>],
+    theme: &Theme,
+) -> Vec<Line<'static>> {
+    let cols = align.len();
+    let mut widths = vec![1; cols];
+    let flatten = |cells: &[Vec<Inline>]| -> Vec<String> {
+        cells.iter().map(|cell| plain_inline(cell, theme)).collect()
+    };
+    let header = flatten(header);
+    let rows: Vec<Vec<String>> = rows.iter().map(|row| flatten(row)).collect();
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        for (i, cell) in row.iter().enumerate().take(cols) {
+            widths[i] = widths[i]
+                .max(display_width(cell))
+                .min(theme.max_col_width.max(1));
+        }
+    }
+
+    let mut lines = vec![table_border('┌', '┬', '┐', &widths, theme)];
+    lines.push(table_row_line(&header, align, &widths, theme.bold, theme));
+    lines.push(table_border('├', '┼', '┤', &widths, theme));
+    for row in &rows {
+        lines.push(table_row_line(row, align, &widths, theme.text, theme));
+    }
+    lines.push(table_border('└', '┴', '┘', &widths, theme));
+    lines
+}
+
+/// one horizontal border of the table grid
+fn table_border(left: char, mid: char, right: char, widths: &[usize], theme: &Theme) -> Line<'static> {
+    let mut out = String::new();
+    out.push(left);
+    for (i, width) in widths.iter().enumerate() {
+        if i > 0 {
+            out.push(mid);
+        }
+        out.push_str(&"─".repeat(width + 2));
+    }
+    out.push(right);
+    Line::from(Span::styled(out, theme.rule))
+}
+
+/// one content row of the table grid, each cell padded to its column
+/// width per the column alignment
+fn table_row_line(
+    cells: &[String],
+    align: &[Align],
+    widths: &[usize],
+    base: Style,
+    theme: &Theme,
+) -> Line<'static> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    for (i, width) in widths.iter().enumerate() {
+        spans.push(Span::styled(
+            if i == 0 { "│ " } else { " │ " }.to_string(),
+            theme.rule,
+        ));
+        let content = cells.get(i).map(String::as_str).unwrap_or("");
+        let align = align.get(i).copied().unwrap_or(Align::Left);
+        spans.push(Span::styled(fit_cell(content, *width, align), base));
+    }
+    spans.push(Span::styled(" │".to_string(), theme.rule));
+    Line::from(spans)
+}
+
+/// pad or truncate cell content to exactly `width` columns
+fn fit_cell(content: &str, width: usize, align: Align) -> String {
+    let seen = display_width(content);
+    if seen > width {
+        let mut out = String::new();
+        let mut used = 0;
+        for c in content.chars() {
+            let w = char_width(c);
+            if used + w > width.saturating_sub(1) {
+                break;
+            }
+            out.push(c);
+            used += w;
+        }
+        out.push('…');
+        // a wide char cut in half leaves a column short, pad it back
+        for _ in used + 1..width {
+            out.push(' ');
+        }
+        return out;
+    }
+    let pad = width - seen;
+    match align {
+        Align::Left => format!("{}{}", content, " ".repeat(pad)),
+        Align::Right => format!("{}{}", " ".repeat(pad), content),
+        Align::Center => format!(
+            "{}{}{}",
+            " ".repeat(pad / 2),
+            content,
+            " ".repeat(pad - pad / 2)
+        ),
+    }
+}
+
+/// the number of terminal columns a string occupies, wide CJK chars
+/// count for two cells and zero-width chars for none
+fn display_width(s: &str) -> usize {
+    unicode_width::UnicodeWidthStr::width(s)
+}
+
+/// the number of terminal columns a single char occupies
+fn char_width(c: char) -> usize {
+    unicode_width::UnicodeWidthChar::width(c).unwrap_or(0)
+}
+
+/// the plain text counterpart of `push_list`
+fn push_plain_list(
+    ordered: bool,
+    items: &[ListItem],
+    tight: bool,
+    out: &mut Vec<String>,
+    theme: &Theme,
+    depth: usize,
+) {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 && !tight {
+            out.push(String::new());
+        }
+        let marker = if ordered {
+            format!("{}. ", i + 1)
+        } else {
+            format!("{} ", theme.bullet)
+        };
+        let task = match item.checked {
+            Some(true) => format!("{} ", theme.task_checked),
+            Some(false) => format!("{} ", theme.task_unchecked),
+            None => String::new(),
+        };
+        let indent = " ".repeat(depth * theme.list_indent);
+        out.push(format!(
+            "{indent}{marker}{task}{}",
+            plain_inline(&item.inline, theme)
+        ));
+        for child in &item.children {
+            match child {
+                Node::List {
+                    ordered,
+                    items,
+                    tight,
+                } => push_plain_list(*ordered, items, *tight, out, theme, depth + 1),
+                other => {
+                    out.push(String::new());
+                    let indent = " ".repeat((depth + 1) * theme.list_indent);
+                    for line in plain_lines(other, theme) {
+                        out.push(format!("{indent}{line}"));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// the plain text counterpart of `inline_spans`, must produce the exact
+/// character sequence the spans would so wrapped counts line up
+fn plain_inline(inline: &[Inline], theme: &Theme) -> String {
+    let mut out = String::new();
+    for node in inline {
+        match node {
+            Inline::Text(text) => out.push_str(text),
+            Inline::SoftBreak | Inline::HardBreak => out.push(' '),
+            Inline::Bold(inner) | Inline::Italic(inner) => {
+                out.push_str(&plain_inline(inner, theme));
+            }
+            Inline::Code(code) => out.push_str(code),
+            Inline::Link { text, href, .. } => {
+                if theme.hyperlinks {
+                    out.push_str(&format!("\x1b]8;;{href}\x1b\\"));
+                    out.push_str(&plain_inline(text, theme));
+                    out.push_str("\x1b]8;;\x1b\\");
+                } else {
+                    out.push_str(&plain_inline(text, theme));
+                    out.push_str(&format!(" ({href})"));
+                }
+            }
+            Inline::Mention(name) => {
+                out.push('@');
+                out.push_str(name);
+            }
+            Inline::FootnoteRef(label) => {
+                out.push_str(&format!("[{label}]"));
+            }
+            Inline::Html(raw) => {
+                if theme.show_html {
+                    out.push_str(raw);
+                }
+            }
+            Inline::Image { alt, .. } => {
+                out.push_str(&format!("{} {alt}", theme.image_glyph));
+            }
+            Inline::Superscript(inner) => {
+                let plain = plain_inline(inner, theme);
+                out.push_str(&superscript(&plain).unwrap_or(plain));
+            }
+            Inline::Subscript(inner) => {
+                let plain = plain_inline(inner, theme);
+                out.push_str(&subscript(&plain).unwrap_or(plain));
+            }
+            Inline::Math(body) => out.push_str(body),
+        }
+    }
+    out
+}
+
+/// the number of lines `wrap_spans` would produce for `content`, the
+/// greedy walk below mirrors it step for step
+fn wrap_count(content: &str, width: usize) -> usize {
+    let chars: Vec<char> = content.chars().collect();
+    let mut count = 0;
+    let mut cur = 0usize;
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == ' ' {
+            if cur != 0 && cur < width {
+                cur += 1;
+            }
+            i += 1;
+            continue;
+        }
+        let mut j = i;
+        while j < chars.len() && chars[j] != ' ' {
+            j += 1;
+        }
+        let word_width: usize = chars[i..j].iter().map(|c| char_width(*c)).sum();
+        if cur != 0 && cur + word_width > width {
+            count += 1;
+            cur = 0;
+        }
+        if word_width > width {
+            for c in &chars[i..j] {
+                let w = char_width(*c);
+                if cur != 0 && cur + w > width {
+                    count += 1;
+                    cur = 0;
+                }
+                cur += w;
+            }
+        } else {
+            cur += word_width;
+        }
+        i = j;
+    }
+    if cur != 0 || count == 0 {
+        count += 1;
+    }
+    count
+}
+
+/// greedily wrap styled spans at `width` columns, breaking on whitespace
+/// and only splitting words longer than a whole line
+fn wrap_spans(spans: &[Span<'static>], width: usize, config: &WrapConfig) -> Vec<Line<'static>> {
+    let chars: Vec<(char, Style)> = spans
+        .iter()
+        .flat_map(|s| s.content.chars().map(move |c| (c, s.style)))
+        .collect();
+
+    let mut out: Vec<Line<'static>> = Vec::new();
+    let mut cur: Vec<(char, Style)> = Vec::new();
+    // the column count of `cur`, tracked separately because wide chars
+    // occupy two cells
+    let mut cur_w = 0usize;
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].0 == ' ' {
+            // spaces are kept inline but dropped at a break
+            if cur_w != 0 && cur_w < width {
+                cur.push(chars[i]);
+                cur_w += 1;
+            }
+            i += 1;
+            continue;
+        }
+        let mut j = i;
+        while j < chars.len() && chars[j].0 != ' ' {
+            j += 1;
+            // a separator ends the wrap unit, keeping it on the line
+            // it trails
+            if config.break_on_punct && matches!(chars[j - 1].0, '-' | '/') {
+                break;
+            }
+        }
+        let word_width: usize = chars[i..j].iter().map(|c| char_width(c.0)).sum();
+        if cur_w != 0 && cur_w + word_width > width {
+            while cur.last().map(|c| c.0 == ' ').unwrap_or(false) {
+                cur.pop();
+            }
+            out.push(chars_to_line(std::mem::take(&mut cur)));
+            cur_w = 0;
+        }
+        if word_width > width {
+            // a word wider than the whole line hard-breaks
+            for ch in &chars[i..j] {
+                let w = char_width(ch.0);
+                if cur_w != 0 && cur_w + w > width {
+                    out.push(chars_to_line(std::mem::take(&mut cur)));
+                    cur_w = 0;
+                }
+                cur.push(*ch);
+                cur_w += w;
+            }
+        } else {
+            cur.extend_from_slice(&chars[i..j]);
+            cur_w += word_width;
+        }
+        i = j;
+    }
+    while cur.last().map(|c| c.0 == ' ').unwrap_or(false) {
+        cur.pop();
+    }
+    if !cur.is_empty() || out.is_empty() {
+        out.push(chars_to_line(cur));
+    }
+    out
+}
+
+/// rebuild spans from styled chars, merging runs that share a style
+fn chars_to_line(chars: Vec<(char, Style)>) -> Line<'static> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut text = String::new();
+    let mut style: Option<Style> = None;
+    for (ch, ch_style) in chars {
+        match style {
+            Some(cur_style) if cur_style == ch_style => text.push(ch),
+            Some(cur_style) => {
+                spans.push(Span::styled(std::mem::take(&mut text), cur_style));
+                text.push(ch);
+                style = Some(ch_style);
+            }
+            None => {
+                text.push(ch);
+                style = Some(ch_style);
+            }
+        }
+    }
+    if let Some(cur_style) = style {
+        spans.push(Span::styled(text, cur_style));
+    }
+    Line::from(spans)
+}
+
+/// emit the lines for one list level, nested sublists are indented one
+/// step further
+fn push_list(
+    ordered: bool,
+    items: &[ListItem],
+    tight: bool,
+    lines: &mut Vec<Line<'static>>,
+    theme: &Theme,
+    depth: usize,
+) {
+    for (i, item) in items.iter().enumerate() {
+        // loose lists space their items apart
+        if i > 0 && !tight {
+            lines.push(Line::from(String::new()));
+        }
+        let marker = if ordered {
+            format!("{}. ", i + 1)
+        } else {
+            format!("{} ", theme.bullet)
+        };
+        let indent = " ".repeat(depth * theme.list_indent);
+        let mut spans = vec![Span::styled(format!("{indent}{marker}"), theme.list)];
+        if let Some(checked) = item.checked {
+            let glyph = if checked {
+                theme.task_checked
+            } else {
+                theme.task_unchecked
+            };
+            spans.push(Span::styled(format!("{glyph} "), theme.list));
+        }
+        spans.extend(inline_spans(&item.inline, theme.text, theme));
+        lines.push(Line::from(spans));
+        for child in &item.children {
+            match child {
+                Node::List {
+                    ordered,
+                    items,
+                    tight,
+                } => push_list(*ordered, items, *tight, lines, theme, depth + 1),
+                // a later paragraph of the same item sits indented
+                // below it after a blank line
+                other => {
+                    lines.push(Line::from(String::new()));
+                    let indent = " ".repeat((depth + 1) * theme.list_indent);
+                    for line in to_text(std::slice::from_ref(other), Some(theme)).lines {
+                        let mut spans = vec![Span::styled(indent.clone(), theme.text)];
+                        spans.extend(line.spans);
+                        lines.push(Line::from(spans));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// the unicode superscript form of `text`, `None` when any character
+/// has no superscript codepoint — terminals cannot shift a baseline so
+/// the dedicated forms are the best approximation available
+fn superscript(text: &str) -> Option<String> {
+    text.chars()
+        .map(|c| match c {
+            '0' => Some('⁰'),
+            '1' => Some('¹'),
+            '2' => Some('²'),
+            '3' => Some('³'),
+            '4' => Some('⁴'),
+            '5' => Some('⁵'),
+            '6' => Some('⁶'),
+            '7' => Some('⁷'),
+            '8' => Some('⁸'),
+            '9' => Some('⁹'),
+            '+' => Some('⁺'),
+            '-' => Some('⁻'),
+            '=' => Some('⁼'),
+            '(' => Some('⁽'),
+            ')' => Some('⁾'),
+            'n' => Some('ⁿ'),
+            'i' => Some('ⁱ'),
+            _ => None,
+        })
+        .collect()
+}
+
+/// the unicode subscript form of `text`, the counterpart of
+/// `superscript`
+fn subscript(text: &str) -> Option<String> {
+    text.chars()
+        .map(|c| match c {
+            '0' => Some('₀'),
+            '1' => Some('₁'),
+            '2' => Some('₂'),
+            '3' => Some('₃'),
+            '4' => Some('₄'),
+            '5' => Some('₅'),
+            '6' => Some('₆'),
+            '7' => Some('₇'),
+            '8' => Some('₈'),
+            '9' => Some('₉'),
+            '+' => Some('₊'),
+            '-' => Some('₋'),
+            '=' => Some('₌'),
+            '(' => Some('₍'),
+            ')' => Some('₎'),
+            _ => None,
+        })
+        .collect()
+}
+
+/// flatten inline nodes into styled spans, `base` carries the styles
+/// accumulated from enclosing emphasis
+fn inline_spans(inline: &[Inline], base: Style, theme: &Theme) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    for node in inline {
+        match node {
+            Inline::Text(text) => spans.push(Span::styled(text.clone(), base)),
+            // soft breaks join their lines with a space, a hard break only
+            // reaches here when nested below the paragraph level
+            Inline::SoftBreak | Inline::HardBreak => {
+                spans.push(Span::styled(" ".to_string(), base))
+            }
+            Inline::Bold(inner) => {
+                spans.extend(inline_spans(inner, base.patch(theme.bold), theme));
+            }
+            Inline::Italic(inner) => {
+                spans.extend(inline_spans(inner, base.patch(theme.italic), theme));
+            }
+            Inline::Code(code) => spans.push(Span::styled(code.clone(), theme.code)),
+            Inline::Link { text, href, .. } => {
+                if theme.hyperlinks {
+                    // OSC 8 wraps the styled text so the terminal makes it
+                    // clickable
+                    spans.push(Span::raw(format!("\x1b]8;;{href}\x1b\\")));
+                    spans.extend(inline_spans(text, base.patch(theme.link), theme));
+                    spans.push(Span::raw("\x1b]8;;\x1b\\".to_string()));
+                } else {
+                    spans.extend(inline_spans(text, base.patch(theme.link), theme));
+                    spans.push(Span::styled(format!(" ({href})"), base));
+                }
+            }
+            Inline::Mention(name) => {
+                spans.push(Span::styled(format!("@{name}"), base.patch(theme.mention)))
+            }
+            Inline::FootnoteRef(label) => {
+                spans.push(Span::styled(format!("[{label}]"), base.patch(theme.link)))
+            }
+            Inline::Html(raw) => {
+                if theme.show_html {
+                    spans.push(Span::styled(raw.clone(), base.patch(theme.html)))
+                }
+            }
+            Inline::Image { alt, .. } => spans.push(Span::styled(
+                format!("{} {alt}", theme.image_glyph),
+                base.patch(theme.image),
+            )),
+            Inline::Superscript(inner) => {
+                let plain = plain_inline(inner, theme);
+                match superscript(&plain) {
+                    Some(mapped) => spans.push(Span::styled(mapped, base)),
+                    // no dedicated forms, fall back to italic styling
+                    None => spans.extend(inline_spans(inner, base.patch(theme.italic), theme)),
+                }
+            }
+            Inline::Subscript(inner) => {
+                let plain = plain_inline(inner, theme);
+                match subscript(&plain) {
+                    Some(mapped) => spans.push(Span::styled(mapped, base)),
+                    None => spans.extend(inline_spans(inner, base.patch(theme.italic), theme)),
+                }
+            }
+            Inline::Math(body) => {
+                spans.push(Span::styled(body.clone(), base.patch(theme.math)))
+            }
+        }
+    }
+    spans
+}
+
+/// the title-bar style for a callout kind
+fn callout_style(kind: CalloutKind, theme: &Theme) -> Style {
+    match kind {
+        CalloutKind::Note => theme.callout_note,
+        CalloutKind::Tip => theme.callout_tip,
+        CalloutKind::Important => theme.callout_important,
+        CalloutKind::Warning => theme.callout_warning,
+        CalloutKind::Caution => theme.callout_caution,
+        CalloutKind::Generic => theme.callout,
+    }
+}
+
+fn heading_style(level: usize, theme: &Theme) -> Style {
+    theme.heading[level.clamp(1, 6) - 1]
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::{Ok, Result};
+    use ratatui::{
+        style::{Color, Style},
+        text::Span,
+    };
+
+    use crate::{
+        parser::{ast::Parser, lexer::Lexer},
+        style::style::Theme,
+    };
+
+    use super::{to_line, to_text, to_text_windowed, to_text_wrapped, to_text_wrapped_with, WrapConfig};
+
+    fn contents(text: &ratatui::text::Text<'_>) -> Vec<String> {
+        text.lines
+            .iter()
+            .map(|l| {
+                l.spans
+                    .iter()
+                    .map(|s| s.content.to_string())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    fn nodes(md: &str) -> Result<Vec<crate::parser::ast::Node>> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse(md)?;
+        let mut parser = Parser::new(tokens);
+        Ok(parser.parse()?)
+    }
+
+    #[test]
+    fn image_placeholder() -> Result<()> {
+        let text = to_text(&nodes("![logo](img.png)")?, None);
+        let theme = Theme::default();
+
+        assert_eq!(
+            text.lines[0].spans,
+            vec![Span::styled("🖼 logo".to_string(), theme.image)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn supsub_rendering() -> Result<()> {
+        assert_eq!(contents(&to_text(&nodes("x^2^")?, None)), vec!["x²"]);
+        assert_eq!(contents(&to_text(&nodes("H~2~O")?, None)), vec!["H₂O"]);
+        // no dedicated forms, the text survives via the style fallback
+        assert_eq!(contents(&to_text(&nodes("x^th^")?, None)), vec!["xth"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn cell_map_tracks_source() -> Result<()> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.parse_spanned("# Title\n\npara")?;
+        let mut parser = Parser::new_spanned(tokens);
+        let nodes = parser.parse_spanned()?;
+
+        let (text, map) = super::to_text_with_map(&nodes, None);
+
+        assert_eq!(text.lines.len(), 3);
+        // the heading row maps into the heading's source span
+        let heading_span = nodes[0].1.clone().unwrap();
+        assert_eq!(map[0].first_row, 0);
+        assert_eq!(map[0].rows, 1);
+        assert_eq!(map[0].span, Some(heading_span.clone()));
+        assert_eq!(super::source_offset(&map, 0), Some(heading_span.start));
+        // the spacing row between the blocks belongs to neither
+        assert_eq!(super::source_offset(&map, 1), None);
+        // the paragraph row maps past the heading
+        assert!(super::source_offset(&map, 2).unwrap() >= 9);
+        assert_eq!(super::source_offset(&map, 5), None);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "bidi")]
+    #[test]
+    fn rtl_paragraph() -> Result<()> {
+        let text = to_text(&nodes("سلام")?, None);
+
+        assert_eq!(
+            text.lines[0].alignment,
+            Some(ratatui::layout::Alignment::Right)
+        );
+        // the single rtl run comes back in visual order
+        assert_eq!(contents(&text), vec!["مالس".to_string()]);
+
+        // latin text is untouched
+        let text = to_text(&nodes("hello")?, None);
+        assert_eq!(text.lines[0].alignment, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn single_line_truncation() -> Result<()> {
+        let nodes = nodes("# Title **bold** text")?;
+        let theme = Theme::default();
+
+        let line = to_line(&nodes, None, 10);
+        assert_eq!(
+            line.spans,
+            vec![
+                Span::styled("Title".to_string(), theme.heading[0]),
+                Span::styled(" bol".to_string(), theme.heading[0].patch(theme.bold)),
+                Span::styled("…".to_string(), theme.text),
+            ]
+        );
+
+        // a fitting document is untouched
+        let line = to_line(&nodes, None, 40);
+        assert_eq!(
+            line.spans
+                .iter()
+                .map(|s| s.content.to_string())
+                .collect::<String>(),
+            "Title bold text"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn small_document() -> Result<()> {
+        let nodes = nodes("# T\nsome **bold**")?;
+
+        let text = to_text(&nodes, None);
+        let theme = Theme::default();
+
+        assert_eq!(text.lines.len(), 3);
+        assert_eq!(
+            text.lines[0].spans,
+            vec![
+                Span::styled("# ", theme.heading[0]),
+                Span::styled("T", theme.heading[0]),
+            ]
+        );
+        // the default spacing leaves one blank row between blocks
+        assert_eq!(text.lines[1].spans, vec![Span::raw("")]);
+        assert_eq!(
+            text.lines[2].spans,
+            vec![
+                Span::styled("some ", theme.text),
+                Span::styled("bold", theme.text.patch(theme.bold)),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_spacing() -> Result<()> {
+        let nodes = nodes("one\n\ntwo")?;
+
+        let packed = Theme {
+            block_spacing: 0,
+            ..Theme::default()
+        };
+        let text = to_text(&nodes, Some(&packed));
+        assert_eq!(contents(&text), vec!["one", "two"]);
+
+        let airy = Theme {
+            block_spacing: 2,
+            ..Theme::default()
+        };
+        let text = to_text(&nodes, Some(&airy));
+        assert_eq!(contents(&text), vec!["one", "", "", "two"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn break_rendering() -> Result<()> {
+        // a hard break forces a new line, a soft break joins with a space
+        let text = to_text(&nodes("a  \nb")?, None);
+        assert_eq!(contents(&text), vec!["a", "b"]);
+
+        let text = to_text(&nodes("a\nb")?, None);
+        assert_eq!(contents(&text), vec!["a b"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ansi_render() -> Result<()> {
+        let nodes = nodes("# Hi")?;
+
+        // bold magenta, the default h1 style, as an SGR sequence
+        assert_eq!(
+            super::to_ansi(&nodes, None),
+            "\x1b[1;35m# \x1b[0m\x1b[1;35mHi\x1b[0m\n"
+        );
+        assert_eq!(super::to_ansi_plain(&nodes, None), "# Hi\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_ansi_matches_to_ansi() -> Result<()> {
+        let nodes = nodes("# Hi\n\nsome *styled* text\n\n- one\n- two")?;
+
+        let mut out: Vec<u8> = Vec::new();
+        super::write_ansi(&nodes, None, &mut out)?;
+        assert_eq!(String::from_utf8(out)?, super::to_ansi(&nodes, None));
+
+        Ok(())
+    }
+
+    #[test]
+    fn loose_list_spacing() -> Result<()> {
+        let tight = nodes("- a\n- b")?;
+        assert_eq!(contents(&to_text(&tight, None)), vec!["• a", "• b"]);
+
+        let loose = nodes("- a\n\n- b")?;
+        assert_eq!(contents(&to_text(&loose, None)), vec!["• a", "", "• b"]);
+
+        // a second paragraph of an item renders indented below it
+        let multi = nodes("- first\n\n  second")?;
+        assert_eq!(
+            contents(&to_text(&multi, None)),
+            vec!["• first", "", "  second"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn code_line_numbers() -> Result<()> {
+        let nodes = nodes("```\nfirst\nsecond\nthird\n```")?;
+        let theme = Theme {
+            code_line_numbers: true,
+            ..Theme::default()
+        };
+
+        let text = to_text(&nodes, Some(&theme));
+        assert_eq!(
+            contents(&text),
+            vec!["1 │ first", "2 │ second", "3 │ third"]
+        );
+        for line in &text.lines {
+            assert_eq!(line.spans[0].style, theme.code_line_number);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn callout_title_bar() -> Result<()> {
+        let nodes = nodes("> [!WARNING]\n> careful")?;
+        let theme = Theme::default();
+
+        let text = to_text(&nodes, None);
+        assert_eq!(contents(&text), vec!["│ Warning", "│ careful"]);
+        assert_eq!(text.lines[0].spans[0].style, theme.callout_warning);
+        assert_eq!(text.lines[1].spans[0].style, theme.callout_warning);
+
+        Ok(())
+    }
+
+    #[test]
+    fn wrap_at_width() -> Result<()> {
+        let nodes = nodes("aaa bbb ccc ddd")?;
+
+        let text = to_text_wrapped(&nodes, None, 10);
+        assert_eq!(contents(&text), vec!["aaa bbb", "ccc ddd"]);
+
+        // zero width leaves the line alone
+        let text = to_text_wrapped(&nodes, None, 0);
+        assert_eq!(contents(&text), vec!["aaa bbb ccc ddd"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn wrap_keeps_emphasis() -> Result<()> {
+        let nodes = nodes("**aaaa bbbb cccc**")?;
+        let theme = Theme::default();
+
+        let text = to_text_wrapped(&nodes, None, 10);
+        assert_eq!(contents(&text), vec!["aaaa bbbb", "cccc"]);
+        for line in &text.lines {
+            for span in &line.spans {
+                assert_eq!(span.style, theme.text.patch(theme.bold));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn wrap_breaks_after_separators() -> Result<()> {
+        let nodes = nodes("a/very/long/path")?;
+
+        // the default keeps the path as one unbreakable word
+        let text = to_text_wrapped(&nodes, None, 6);
+        assert_eq!(contents(&text), vec!["a/very", "/long/", "path"]);
+
+        let config = WrapConfig {
+            break_on_punct: true,
+        };
+        let text = to_text_wrapped_with(&nodes, None, 6, &config);
+        assert_eq!(contents(&text), vec!["a/", "very/", "long/", "path"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn wrap_breaks_long_word() -> Result<()> {
+        let nodes = nodes("abcdefghijklmno")?;
+
+        let text = to_text_wrapped(&nodes, None, 10);
+        assert_eq!(contents(&text), vec!["abcdefghij", "klmno"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn wrap_measures_display_width() -> Result<()> {
+        assert_eq!(super::display_width("世界"), 4);
+        assert_eq!(super::display_width("hello"), 5);
+
+        // each CJK char is two cells wide, only one word fits per
+        // 5-column line
+        let nodes = nodes("世界 世界 世界")?;
+        let text = to_text_wrapped(&nodes, None, 5);
+        assert_eq!(contents(&text), vec!["世界", "世界", "世界"]);
+        for line in contents(&text) {
+            assert!(super::display_width(&line) <= 5);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_list_indentation() -> Result<()> {
+        let nodes = nodes("- a\n  - b\n- c\n\n1. one")?;
+
+        let text = to_text(&nodes, None);
+
+        let rendered = text
+            .lines
+            .iter()
+            .map(|l| {
+                l.spans
+                    .iter()
+                    .map(|s| s.content.to_string())
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(rendered, vec!["• a", "  • b", "• c", "", "1. one"]);
+
+        // the bullet glyph and indent step are theme controlled
+        let theme = Theme {
+            bullet: '-',
+            list_indent: 4,
+            ..Theme::default()
+        };
+        let text = to_text(&nodes, Some(&theme));
+        assert_eq!(text.lines[1].spans[0].content, "    - ");
+
+        Ok(())
+    }
+
+    #[test]
+    fn windowed_render() -> Result<()> {
+        let md = "# T\n\naaa bbb ccc ddd eee\n\n- one\n- two\n- three\n\n```\nx\ny\n```\n\n---";
+        let nodes = nodes(md)?;
+
+        let full = to_text_wrapped(&nodes, None, 8);
+        assert_eq!(full.lines.len(), 14);
+
+        // the window is exactly the matching slice of the full render
+        let window = to_text_windowed(&nodes, None, 8, 5, 3);
+        assert_eq!(window.lines, full.lines[5..8].to_vec());
+
+        // a window past the end is empty, one over the end is clipped
+        assert!(to_text_windowed(&nodes, None, 8, 14, 3).lines.is_empty());
+        assert_eq!(to_text_windowed(&nodes, None, 8, 12, 5).lines.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rule_glyph_line() -> Result<()> {
+        let nodes = nodes("---")?;
+
+        let text = to_text(&nodes, None);
+        assert_eq!(contents(&text), vec!["─".repeat(80)]);
+
+        // wrapping clips the rule to the target width
+        let text = to_text_wrapped(&nodes, None, 10);
+        assert_eq!(contents(&text), vec!["─".repeat(10)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn task_list_glyphs() -> Result<()> {
+        let nodes = nodes("- [ ] a\n- [x] b")?;
+
+        let text = to_text(&nodes, None);
+        assert_eq!(contents(&text), vec!["• ☐ a", "• ☑ b"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn osc8_hyperlinks() -> Result<()> {
+        let nodes = nodes("[site](http://x.com)")?;
+
+        let theme = Theme {
+            hyperlinks: true,
+            ..Theme::default()
+        };
+        let text = to_text(&nodes, Some(&theme));
+        assert_eq!(
+            contents(&text),
+            vec!["\x1b]8;;http://x.com\x1b\\site\x1b]8;;\x1b\\"]
+        );
+
+        // without hyperlinks the url is spelled out after the text
+        let text = to_text(&nodes, None);
+        assert_eq!(contents(&text), vec!["site (http://x.com)"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn table_grid() -> Result<()> {
+        let nodes = nodes("| name | n |\n| :-- | --: |\n| a | 1 |\n| bb | 22 |")?;
+
+        let text = to_text(&nodes, None);
+
+        assert_eq!(
+            contents(&text),
+            vec![
+                "┌──────┬────┐",
+                "│ name │  n │",
+                "├──────┼────┤",
+                "│ a    │  1 │",
+                "│ bb   │ 22 │",
+                "└──────┴────┘",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn table_truncation() -> Result<()> {
+        let nodes = nodes("| h |\n| --- |\n| long cell |")?;
+
+        let theme = Theme {
+            max_col_width: 5,
+            ..Theme::default()
+        };
+
+        let text = to_text(&nodes, Some(&theme));
+
+        assert_eq!(
+            contents(&text),
+            vec![
+                "┌───────┐",
+                "│ h     │",
+                "├───────┤",
+                "│ long… │",
+                "└───────┘",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "highlight")]
+    #[test]
+    fn highlighted_rust_keyword() -> Result<()> {
+        let nodes = nodes("```rust\nfn main() {}\n```")?;
+
+        let text = to_text(&nodes, None);
+
+        let line = &text.lines[0];
+        assert!(line.spans.len() > 1, "highlighting should split the line");
+        let keyword = line
+            .spans
+            .iter()
+            .find(|s| s.content.trim() == "fn")
+            .expect("the keyword gets its own span");
+        let other = line
+            .spans
+            .iter()
+            .find(|s| s.content.contains("main"))
+            .expect("the identifier gets its own span");
+        assert_ne!(keyword.style, other.style);
+
+        Ok(())
+    }
+
+    #[test]
+    fn blockquote_gutter() -> Result<()> {
+        let nodes = nodes("> some quoted words\n> that keep going")?;
+        let theme = Theme::default();
+
+        let text = to_text(&nodes, None);
+        assert!(!text.lines.is_empty());
+        for line in &text.lines {
+            assert_eq!(line.spans[0].content, "│ ");
+            assert_eq!(line.spans[0].style, theme.quote);
+        }
+
+        // wrapped lines keep the gutter too
+        let text = to_text_wrapped(&nodes, None, 12);
+        assert!(text.lines.len() > 2);
+        for line in &text.lines {
+            assert_eq!(line.spans[0].content, "│ ");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn theme_override() -> Result<()> {
+        let nodes = nodes("# T")?;
+
+        let mut theme = Theme::default();
+        theme.heading[0] = Style::default().fg(Color::Cyan);
+
+        let text = to_text(&nodes, Some(&theme));
+
+        assert_eq!(
+            text.lines[0].spans,
+            vec![
+                Span::styled("# ", Style::default().fg(Color::Cyan)),
+                Span::styled("T", Style::default().fg(Color::Cyan)),
+            ]
+        );
+
+        Ok(())
+    }
+}
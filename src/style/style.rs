@@ -1,5 +1,142 @@
 use ratatui::style::{Color, Modifier, Style};
 
+/// style configuration for the AST renderer, one entry per heading level
+/// plus the inline styles, `bold`/`italic` are patched onto the style of
+/// the surrounding content
+#[derive(Debug, PartialEq, Clone)]
+pub struct Theme {
+    pub heading: [Style; 6],
+    pub text: Style,
+    pub bold: Style,
+    pub italic: Style,
+    pub code: Style,
+    pub link: Style,
+    /// style patched over `@name` mentions
+    pub mention: Style,
+    pub list: Style,
+    pub rule: Style,
+    /// glyph placed before unordered list items
+    pub bullet: char,
+    /// gutter bar drawn before every blockquote line and its style,
+    /// nested quotes stack one bar per level
+    pub quote_glyph: char,
+    pub quote: Style,
+    /// glyphs for rendered task-list checkboxes
+    pub task_unchecked: char,
+    pub task_checked: char,
+    /// glyph a thematic break is drawn with and how many columns it
+    /// spans, `to_text_wrapped` clips it to the wrap width instead
+    pub rule_glyph: char,
+    pub rule_width: usize,
+    /// spaces of indent added per list nesting level
+    pub list_indent: usize,
+    /// widest a table column may grow, longer cell content is truncated
+    /// with `…`
+    pub max_col_width: usize,
+    /// emit OSC 8 escape sequences so links are clickable in supporting
+    /// terminals, otherwise the url is appended after the link text
+    pub hyperlinks: bool,
+    /// syntect theme used for fenced code blocks under the `highlight`
+    /// feature, unknown names fall back to the uniform `code` style
+    pub code_theme: String,
+    /// show raw html blocks and inline tags literally instead of
+    /// stripping them, shown html takes the `html` style
+    pub show_html: bool,
+    pub html: Style,
+    /// style raw latex from `$...$` spans and `$$` blocks is shown in
+    pub math: Style,
+    /// glyph and style an inline image placeholder renders with, the
+    /// alt text follows the glyph
+    pub image_glyph: char,
+    pub image: Style,
+    /// title-bar styles for `> [!NOTE]` style callouts, one per
+    /// recognized kind with `callout` covering unknown kinds
+    pub callout_note: Style,
+    pub callout_tip: Style,
+    pub callout_important: Style,
+    pub callout_warning: Style,
+    pub callout_caution: Style,
+    pub callout: Style,
+    /// prefix each rendered code-block line with a right-aligned line
+    /// number and a separator in the `code_line_number` style, the
+    /// gutter is as wide as the last line's number
+    pub code_line_numbers: bool,
+    pub code_line_number: Style,
+    /// blank lines inserted between rendered blocks, zero packs the
+    /// output tight, independent of the loose/tight list distinction
+    pub block_spacing: u8,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            heading: [
+                Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::BOLD),
+                Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::BOLD),
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+                Style::default()
+                    .fg(Color::LightGreen)
+                    .add_modifier(Modifier::BOLD),
+                Style::default()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD),
+            ],
+            text: Style::default(),
+            bold: Style::default().add_modifier(Modifier::BOLD),
+            italic: Style::default().add_modifier(Modifier::ITALIC),
+            code: Style::default().fg(Color::Gray).bg(Color::Black),
+            link: Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::UNDERLINED),
+            mention: Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+            list: Style::default().fg(Color::LightRed),
+            rule: Style::default().fg(Color::Gray),
+            bullet: '•',
+            quote_glyph: '│',
+            quote: Style::default().fg(Color::Green),
+            task_unchecked: '☐',
+            task_checked: '☑',
+            rule_glyph: '─',
+            rule_width: 80,
+            list_indent: 2,
+            max_col_width: 30,
+            hyperlinks: false,
+            code_theme: "base16-ocean.dark".to_string(),
+            show_html: false,
+            html: Style::default().add_modifier(Modifier::DIM),
+            math: Style::default().fg(Color::Yellow),
+            image_glyph: '🖼',
+            image: Style::default().fg(Color::Magenta),
+            callout_note: Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD),
+            callout_tip: Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+            callout_important: Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+            callout_warning: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            callout_caution: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            callout: Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            code_line_numbers: false,
+            code_line_number: Style::default().add_modifier(Modifier::DIM),
+            block_spacing: 1,
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, PartialEq, Clone)]
 pub struct MdStyle {
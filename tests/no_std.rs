@@ -0,0 +1,17 @@
+//! exercises the `no_std` + `alloc` build of the lexer and parser, the
+//! harness itself links std so this only checks the library, run with
+//! `cargo test --no-default-features --test no_std`
+#![cfg(not(feature = "std"))]
+
+use md_to_tui::parser::{ast, lexer::Lexer};
+
+#[test]
+fn lex_and_parse_without_std() {
+    let mut lexer = Lexer::new();
+    let tokens = lexer.parse("# Title\n\nsome *text*").unwrap();
+
+    let mut parser = ast::Parser::new(tokens);
+    let nodes = parser.parse().unwrap();
+
+    assert_eq!(nodes.len(), 2);
+}